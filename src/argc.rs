@@ -2,26 +2,200 @@ use crate::version::VERSION;
 use clap::{Arg, Command};
 
 pub fn argc_app() -> Command {
+	let app = check_app();
+	let wait = wait_command(&app);
+	app.subcommand(wait)
+}
+
+// `wait` takes every option of a regular check, except --listen
+fn wait_command(app: &Command) -> Command {
+	Command::new("wait")
+		.about("wait until the hosts are reachable, then run a command. Exits non-zero if they are still unreachable after --max-wait")
+		.args(
+			app
+				.get_arguments()
+				.filter(|arg| arg.get_id() != "listen")
+				.cloned(),
+		)
+		.arg(
+			Arg::new("max-wait")
+				.help("how long to keep checking the hosts before giving up. The wait between checks backs off according to --retry-backoff and --retry-max-delay")
+				.long("max-wait")
+				.value_name("60s"),
+		)
+		.arg(
+			Arg::new("command")
+				.help("command to run once the hosts are reachable, it replaces can-i-connect")
+				.value_name("COMMAND")
+				.num_args(1..)
+				.last(true),
+		)
+}
+
+fn check_app() -> Command {
 	Command::new("can-i-connect")
 		.version(VERSION.as_str())
 		.about("tool to check connectivity to various hosts using HTTP or TCP")
+		.arg(
+			Arg::new("targets")
+				.help("comma seperated list of targets to attempt to connect to, as <scheme>://<host>[:<port>] URIs (tcp, tls, udp, http or https). Query parameters override settings for a single target e.g. tcp://db:5432?timeout=2s or https://api/health?expect=200")
+				.long("targets")
+				.value_name("tcp://example.com:80"),
+		)
 		.arg(
 			Arg::new("http-hosts")
-				.help("comma seperated list of http hosts to attempt to connect to")
+				.help("comma seperated list of http hosts to attempt to connect to, same as passing them to --targets")
 				.long("http-hosts")
         .value_name("https://example.com"),
 		)
 		.arg(
 			Arg::new("tcp-hosts")
-				.help("comma seperated list of tcp hosts to attempt to connect to. Required format: <dns name or ip address>:<port>")
+				.help("comma seperated list of tcp hosts to attempt to connect to. Required format: <dns name or ip address>:<port>, same as passing tcp://<host>:<port> to --targets. A CIDR block or port range checks every host in it e.g. 10.20.0.0/29:5432 or db:8000-8010")
 				.long("tcp-hosts")
         .value_name("example.com:80"),
 		)
+		.arg(
+			Arg::new("max-expansion")
+				.help("how many hosts a single tcp host with a CIDR block (10.20.0.0/29:5432) or port range (db:8000-8010) may expand into")
+				.long("max-expansion")
+				.value_name("256"),
+		)
 		.arg(
 			Arg::new("timeout")
-				.help("how much time in seconds to wait while connecting to a host before giving up")
+				.help("how long a single connection attempt may take in total (DNS, connect, TLS handshake and HTTP response) before giving up. Bare numbers are seconds")
 				.long("timeout")
-        .value_name("5"),
+        .value_name("5s"),
+		)
+		.arg(
+			Arg::new("connect-timeout")
+				.help("how long to wait for DNS resolution and the TCP connection, defaults to --timeout")
+				.long("connect-timeout")
+				.value_name("1s"),
+		)
+		.arg(
+			Arg::new("tls-timeout")
+				.help("how long to wait for the TLS handshake of tls:// targets, defaults to --timeout. For https hosts it is added to --connect-timeout, the sum bounds connecting and the handshake together")
+				.long("tls-timeout")
+				.value_name("1s"),
+		)
+		.arg(
+			Arg::new("concurrency")
+				.help("maximum number of hosts to check at the same time")
+				.long("concurrency")
+				.value_name("10"),
+		)
+		.arg(
+			Arg::new("retries")
+				.help("how many times to retry a host after a failed connection attempt")
+				.long("retries")
+				.value_name("0"),
+		)
+		.arg(
+			Arg::new("retry-backoff")
+				.help("how long to wait before the first retry, doubled (with jitter) after every failed attempt")
+				.long("retry-backoff")
+				.value_name("500ms"),
+		)
+		.arg(
+			Arg::new("retry-max-delay")
+				.help("upper bound for the wait between retries")
+				.long("retry-max-delay")
+				.value_name("10s"),
+		)
+		.arg(
+			Arg::new("samples")
+				.help("how many times to connect to every host, reports min/mean/p50/p95/max latency, jitter and loss")
+				.long("samples")
+				.value_name("1"),
+		)
+		.arg(
+			Arg::new("sample-interval")
+				.help("how long to wait between the samples of a host")
+				.long("sample-interval")
+				.value_name("1s"),
+		)
+		.arg(
+			Arg::new("deadline")
+				.help("overall time limit for checking every host, hosts that have not finished by then are reported as cancelled")
+				.long("deadline")
+				.value_name("30s"),
+		)
+		.arg(
+			Arg::new("all-addresses")
+				.help("probe every address a host resolves to instead of just the first one, a host is only reachable if all of its addresses are")
+				.long("all-addresses")
+				.action(clap::ArgAction::SetTrue)
+				.conflicts_with("happy-eyeballs"),
+		)
+		.arg(
+			Arg::new("happy-eyeballs")
+				.help("race the addresses a host resolves to (RFC 8305 Happy Eyeballs) and report which address family won")
+				.long("happy-eyeballs")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("ipv4-only")
+				.help("only connect over IPv4, hosts without an IPv4 address fail")
+				.long("ipv4-only")
+				.action(clap::ArgAction::SetTrue)
+				.conflicts_with_all(["ipv6-only", "prefer-ipv6"]),
+		)
+		.arg(
+			Arg::new("ipv6-only")
+				.help("only connect over IPv6, hosts without an IPv6 address fail")
+				.long("ipv6-only")
+				.action(clap::ArgAction::SetTrue)
+				.conflicts_with("prefer-ipv6"),
+		)
+		.arg(
+			Arg::new("prefer-ipv6")
+				.help("connect over IPv6 when a host has an IPv6 address, falling back to IPv4 (IPv4 is preferred by default)")
+				.long("prefer-ipv6")
+				.action(clap::ArgAction::SetTrue),
+		)
+		.arg(
+			Arg::new("source-address")
+				.help("connect from this local address instead of the one the routing table picks, only hosts of the same IP family can be reached")
+				.long("source-address")
+				.value_name("10.0.0.5"),
+		)
+		.arg(
+			Arg::new("interface")
+				.help("bind every connection to this network interface (SO_BINDTODEVICE, Linux only)")
+				.long("interface")
+				.value_name("eth1"),
+		)
+		.arg(
+			Arg::new("success-policy")
+				.help("when the run counts as a success: all hosts, any host, at least N hosts, at least N% of hosts or at least N hosts per tag group (hosts are tagged with a <tag>= prefix e.g. db=postgres:5432)")
+				.long("success-policy")
+				.value_name("all|any|<N>|<N>%|group:<tag>=<N>,...")
+				.default_value("all"),
+		)
+		.arg(
+			Arg::new("output")
+				.help("how to report results: log lines only, the full report as JSON (the same schema as the /can-i-connect response), a JUnit XML report, TAP (written as each host finishes) or an aligned table. Reports go to stdout, with logs sent to stderr, unless --output-file is set")
+				.long("output")
+				.value_name("log|json|junit|tap|table")
+				.default_value("log"),
+		)
+		.arg(
+			Arg::new("output-file")
+				.help("write the --output report to this file instead of stdout")
+				.long("output-file")
+				.value_name("report.xml"),
+		)
+		.arg(
+			Arg::new("report-html")
+				.help("also write the full report as a single HTML page with no external assets: run metadata, per host results, latency stats and failure reasons")
+				.long("report-html")
+				.value_name("out.html"),
+		)
+		.arg(
+			Arg::new("report-md")
+				.help("also write the full report as a Markdown document")
+				.long("report-md")
+				.value_name("out.md"),
 		)
 		.arg(
 			Arg::new("log-level")
@@ -32,7 +206,7 @@ pub fn argc_app() -> Command {
 		)
 		.arg(
 			Arg::new("no-color")
-				.help("remove color from log output")
+				.help("remove color from log and table output, as does setting NO_COLOR")
 				.long("no-color")
         .action(clap::ArgAction::SetFalse)
 		)
@@ -1,113 +1,805 @@
-use crate::error::Result;
-use crate::helpers::{handle_http, handle_tcp};
+use crate::checker::{Checker, CheckerRegistry, ConnectionInfo, PortState};
+use crate::dns::{AddressFamily, DefaultResolver, DnsResolver};
+use crate::error::{Error, ErrorKind, Result};
+use crate::helpers::{
+	resolve_all, serialize_duration_ms, serialize_timestamp, validate_bind_addr, was_successful,
+};
+#[cfg(feature = "server")]
 use crate::metrics::track_metrics;
-use crate::web;
+use crate::policy::{Outcome, SuccessPolicy};
+use crate::retry::RetryPolicy;
+use crate::source::Source;
+use crate::stats::{LatencyStats, Sampling};
+use crate::target::{validate_targets, ExpectedStatus, Target, DEFAULT_MAX_EXPANSION};
+use crate::timeouts::Timeouts;
+#[cfg(feature = "server")]
+use crate::web::{self, types::ServerLimits};
+#[cfg(feature = "server")]
 use axum::{middleware, Router};
+use futures::stream::{self, Stream, StreamExt};
 use log::{debug, error, info};
 use reqwest::Client;
-use std::net::SocketAddr;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{self, sleep};
+
+// region: constants
+pub const DEFAULT_CONCURRENCY: usize = 10;
+
+// endregion: constants
 
 // region: enums
-pub enum ConnectionType {
-	HTTP,
-	TCP,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStatus {
+	Success,
+	Failed,
+	// the report deadline was reached before the host finished
+	Cancelled,
+	// a must-fail host could not be connected to, as expected
+	Blocked,
+	// a must-fail host could be connected to
+	UnexpectedlyReachable,
+}
+
+// how a must-fail host was blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+	// the connection attempt timed out, packets are silently dropped
+	Dropped,
+	// the connection was actively rejected (TCP RST or ICMP unreachable)
+	Rejected,
+	// the host name did not resolve
+	Unresolved,
+	// any other failure e.g. a failed TLS handshake
+	Other,
+}
+
+// which of the addresses a host resolves to get probed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressMode {
+	// the first address (IPv4 preferred)
+	#[default]
+	First,
+	// every address, the host is only reachable if all of them are
+	All,
+	// race the addresses RFC 8305 style, the first to connect wins
+	HappyEyeballs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+	Ipv4,
+	Ipv6,
+}
+
+// progress of a connection report, sent as it happens
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProbeEvent {
+	// a host is being checked. One without a matching `ProbeFinished` is still running
+	ProbeStarted {
+		// position of the host in the report
+		index: usize,
+		target: String,
+		protocol: String,
+		tag: Option<String>,
+		#[serde(serialize_with = "serialize_timestamp")]
+		started_at: SystemTime,
+	},
+	// a host finished, or was cancelled when the deadline was reached
+	ProbeFinished {
+		index: usize,
+		result: Box<ProbeResult>,
+	},
+	// every host has finished, always the last event
+	ReportComplete {
+		hosts_total: usize,
+		hosts_successful: usize,
+		hosts_failed: usize,
+		hosts_cancelled: usize,
+		#[serde(rename = "elapsed_ms", serialize_with = "serialize_duration_ms")]
+		elapsed: Duration,
+	},
 }
 
 // endregion: enums
 
 // region: structs
+// built with `CanIConnect::builder()`, new settings can be added without breaking callers
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct CanIConnect {
 	pub http: Vec<String>,
 	pub tcp: Vec<String>,
-	pub timeout: usize,
+	// targets of any scheme, see `Target`
+	pub targets: Vec<Target>,
+	// how many targets a tcp host with a CIDR block or port range may expand into
+	pub max_expansion: usize,
+	pub timeouts: Timeouts,
+	pub concurrency: usize,
+	pub retry: RetryPolicy,
+	pub sampling: Sampling,
+	pub deadline: Option<Duration>,
+	pub address_mode: AddressMode,
+	pub family: AddressFamily,
+	// the local address and/or interface connections are made from
+	pub source: Source,
+	// http(s) targets fail when they respond with any other status
+	pub expect_status: Option<ExpectedStatus>,
 	pub server_mode: bool,
 	pub listen_addr: String,
 	pub http_client: Option<Client>,
+	// resolves host names, the system resolver is used when not set
+	pub resolver: Option<Arc<dyn DnsResolver>>,
+	// how targets are checked, keyed by URL scheme
+	pub checkers: CheckerRegistry,
+}
+
+// builds a CanIConnect, starting from the same defaults as the CLI
+#[derive(Debug, Clone)]
+pub struct CanIConnectBuilder {
+	can_i_connect: CanIConnect,
+}
+
+// outcome of checking a single host
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+	pub target: String,
+	// the protocol of the checker that handled the host e.g. `tcp`
+	pub protocol: String,
+	pub tag: Option<String>,
+	// the CIDR block or port range the host was expanded from
+	pub expanded_from: Option<String>,
+	// the check passes when the host can NOT be connected to
+	pub must_fail: bool,
+	pub status: ProbeStatus,
+	pub success: bool,
+	pub resolved_ip: Option<IpAddr>,
+	pub family: Option<IpFamily>,
+	// the local address the connection was made from
+	pub local_ip: Option<IpAddr>,
+	// the response status of http(s) hosts
+	pub http_status: Option<u16>,
+	// whether udp hosts replied (`open`) or stayed silent (`open|filtered`)
+	pub port_state: Option<PortState>,
+	#[serde(rename = "latency_ms", serialize_with = "serialize_duration_ms")]
+	pub latency: Duration,
+	pub error_kind: Option<ErrorKind>,
+	pub error: Option<String>,
+	// how a must-fail host was blocked
+	pub blocked: Option<BlockKind>,
+	// per address results when every resolved address is probed
+	pub addresses: Vec<AddressResult>,
+	pub attempts: u32,
+	pub attempt_errors: Vec<AttemptError>,
+	// latency statistics when the host is connected to more than once
+	pub stats: Option<LatencyStats>,
+	#[serde(serialize_with = "serialize_timestamp")]
+	pub started_at: SystemTime,
+	#[serde(serialize_with = "serialize_timestamp")]
+	pub finished_at: SystemTime,
+}
+
+// outcome of checking one of the addresses a host resolved to
+#[derive(Debug, Clone, Serialize)]
+pub struct AddressResult {
+	pub address: IpAddr,
+	pub family: IpFamily,
+	pub success: bool,
+	#[serde(rename = "latency_ms", serialize_with = "serialize_duration_ms")]
+	pub latency: Duration,
+	pub error_kind: Option<ErrorKind>,
+	pub error: Option<String>,
+}
+
+// why a single connection attempt failed
+#[derive(Debug, Clone, Serialize)]
+pub struct AttemptError {
+	pub attempt: u32,
+	pub error_kind: ErrorKind,
+	pub error: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct ConnectionReport {
-	pub successful_hosts: Vec<String>,
-	pub failed_hosts: Vec<String>,
+	pub results: Vec<ProbeResult>,
+}
+
+// how the hosts a CIDR block or port range expanded into fared
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpansionSummary {
+	pub spec: String,
+	pub hosts_total: usize,
+	pub hosts_successful: usize,
+	pub failed_hosts_list: Vec<String>,
 }
 
 // endregion: structs
 
 // region: methods
+impl Default for CanIConnect {
+	fn default() -> CanIConnect {
+		CanIConnect {
+			http: vec![],
+			tcp: vec![],
+			targets: vec![],
+			max_expansion: DEFAULT_MAX_EXPANSION,
+			timeouts: Timeouts::default(),
+			concurrency: DEFAULT_CONCURRENCY,
+			retry: RetryPolicy::default(),
+			sampling: Sampling::default(),
+			deadline: None,
+			address_mode: AddressMode::default(),
+			family: AddressFamily::default(),
+			source: Source::default(),
+			expect_status: None,
+			server_mode: false,
+			listen_addr: String::from(""),
+			http_client: None,
+			resolver: None,
+			checkers: CheckerRegistry::default(),
+		}
+	}
+}
+
+impl CanIConnectBuilder {
+	// hosts are `<url>` optionally prefixed with `!` (must fail) and/or `<tag>=`
+	pub fn http_hosts<I, S>(mut self, hosts: I) -> CanIConnectBuilder
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self
+			.can_i_connect
+			.http
+			.extend(hosts.into_iter().map(Into::into));
+		self
+	}
+	// hosts are `<host>:<port>` optionally prefixed with `!` (must fail) and/or `<tag>=`
+	pub fn tcp_hosts<I, S>(mut self, hosts: I) -> CanIConnectBuilder
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self
+			.can_i_connect
+			.tcp
+			.extend(hosts.into_iter().map(Into::into));
+		self
+	}
+	pub fn http_host(self, host: impl Into<String>) -> CanIConnectBuilder {
+		self.http_hosts([host])
+	}
+	pub fn tcp_host(self, host: impl Into<String>) -> CanIConnectBuilder {
+		self.tcp_hosts([host])
+	}
+	// targets of any scheme e.g. `"tls://ldap:636".parse::<Target>()?`
+	pub fn targets(mut self, targets: impl IntoIterator<Item = Target>) -> CanIConnectBuilder {
+		self.can_i_connect.targets.extend(targets);
+		self
+	}
+	pub fn target(self, target: Target) -> CanIConnectBuilder {
+		self.targets([target])
+	}
+	// how many targets a tcp host with a CIDR block or port range may expand into, see
+	// `Target::expand`
+	pub fn max_expansion(mut self, max_expansion: usize) -> CanIConnectBuilder {
+		self.can_i_connect.max_expansion = max_expansion;
+		self
+	}
+	// time limit for a single connection attempt, see `Timeouts`
+	pub fn timeout(mut self, timeout: Duration) -> CanIConnectBuilder {
+		self.can_i_connect.timeouts.total = timeout;
+		self
+	}
+	pub fn connect_timeout(mut self, timeout: Duration) -> CanIConnectBuilder {
+		self.can_i_connect.timeouts.connect = Some(timeout);
+		self
+	}
+	pub fn tls_timeout(mut self, timeout: Duration) -> CanIConnectBuilder {
+		self.can_i_connect.timeouts.tls = Some(timeout);
+		self
+	}
+	pub fn timeouts(mut self, timeouts: Timeouts) -> CanIConnectBuilder {
+		self.can_i_connect.timeouts = timeouts;
+		self
+	}
+	pub fn concurrency(mut self, concurrency: usize) -> CanIConnectBuilder {
+		self.can_i_connect.concurrency = concurrency;
+		self
+	}
+	pub fn retry(mut self, retry: RetryPolicy) -> CanIConnectBuilder {
+		self.can_i_connect.retry = retry;
+		self
+	}
+	pub fn sampling(mut self, sampling: Sampling) -> CanIConnectBuilder {
+		self.can_i_connect.sampling = sampling;
+		self
+	}
+	pub fn deadline(mut self, deadline: Duration) -> CanIConnectBuilder {
+		self.can_i_connect.deadline = Some(deadline);
+		self
+	}
+	pub fn address_mode(mut self, address_mode: AddressMode) -> CanIConnectBuilder {
+		self.can_i_connect.address_mode = address_mode;
+		self
+	}
+	pub fn family(mut self, family: AddressFamily) -> CanIConnectBuilder {
+		self.can_i_connect.family = family;
+		self
+	}
+	// connect from this local address, hosts of the other IP family can not be reached
+	pub fn source_address(mut self, address: IpAddr) -> CanIConnectBuilder {
+		self.can_i_connect.source.address = Some(address);
+		self
+	}
+	// bind every socket to this network interface (`SO_BINDTODEVICE`, Linux only)
+	pub fn interface(mut self, interface: impl Into<String>) -> CanIConnectBuilder {
+		self.can_i_connect.source.interface = Some(interface.into());
+		self
+	}
+	pub fn source(mut self, source: Source) -> CanIConnectBuilder {
+		self.can_i_connect.source = source;
+		self
+	}
+	pub fn expect_status(mut self, expect_status: ExpectedStatus) -> CanIConnectBuilder {
+		self.can_i_connect.expect_status = Some(expect_status);
+		self
+	}
+	// http hosts are requested with this client as is, so it picks the address and family itself
+	pub fn http_client(mut self, http_client: Client) -> CanIConnectBuilder {
+		self.can_i_connect.http_client = Some(http_client);
+		self
+	}
+	// check targets with the given URL scheme with `checker`, see `Checker`
+	pub fn checker(mut self, scheme: &str, checker: impl Checker + 'static) -> CanIConnectBuilder {
+		self.can_i_connect.checkers.register(scheme, checker);
+		self
+	}
+	pub fn resolver(mut self, resolver: impl DnsResolver + 'static) -> CanIConnectBuilder {
+		self.can_i_connect.resolver = Some(Arc::new(resolver));
+		self
+	}
+	// run in server mode, see `CanIConnect::bind`
+	#[cfg(feature = "server")]
+	pub fn listen(mut self, listen_addr: &str) -> CanIConnectBuilder {
+		self.can_i_connect.server_mode = true;
+		self.can_i_connect.listen_addr = listen_addr.to_string();
+		self
+	}
+	pub fn build(self) -> Result<CanIConnect> {
+		let can_i_connect = self.can_i_connect;
+		if can_i_connect.concurrency == 0 {
+			return Err(Error::InvalidConcurrency(String::from("0")));
+		}
+		if can_i_connect.sampling.samples == 0 {
+			return Err(Error::InvalidSamples(String::from("0")));
+		}
+		if can_i_connect.max_expansion == 0 {
+			return Err(Error::InvalidMaxExpansion(String::from("0")));
+		}
+		can_i_connect.source.check_family(can_i_connect.family)?;
+		can_i_connect.validate()?;
+		if can_i_connect.server_mode {
+			validate_bind_addr(&can_i_connect.listen_addr)?;
+		} else if can_i_connect.hosts_total() == 0 {
+			return Err(Error::NoHostsSupplied);
+		}
+		Ok(can_i_connect)
+	}
+}
+
 impl CanIConnect {
-	pub async fn can_connect(
-		self: &Self,
-		connection_type: ConnectionType,
-		host: &String,
-	) -> Result<bool> {
-		let http_clinet_ref = self.http_client.as_ref();
-		match connection_type {
-			ConnectionType::HTTP => {
-				return handle_http(host, http_clinet_ref, self.timeout).await;
-			}
-			ConnectionType::TCP => {
-				return handle_tcp(host, self.timeout).await;
+	pub fn builder() -> CanIConnectBuilder {
+		CanIConnectBuilder {
+			can_i_connect: CanIConnect::default(),
+		}
+	}
+	// the resolver used for every lookup made by can-i-connect itself
+	pub fn resolver(&self) -> &dyn DnsResolver {
+		match &self.resolver {
+			Some(resolver) => resolver.as_ref(),
+			None => &DefaultResolver,
+		}
+	}
+	// the protocol reported for targets with the given URL scheme
+	fn protocol(&self, scheme: &str) -> String {
+		match self.checkers.get(scheme) {
+			Some(checker) => checker.protocol().to_string(),
+			None => scheme.to_string(),
+		}
+	}
+	// the address family hosts are connected over, a source address only reaches its own family
+	pub fn family(&self) -> AddressFamily {
+		self.source.family(self.family)
+	}
+	// every address a `<host>:<port>` resolves to that can be connected to
+	pub async fn resolve(&self, authority: &str) -> Result<Vec<SocketAddr>> {
+		resolve_all(
+			self.resolver(),
+			authority,
+			self.timeouts.connect(),
+			self.family(),
+		)
+		.await
+		.map_err(|e| self.source.family_error(authority, e))
+	}
+	// the checker for targets with the given URL scheme
+	pub fn checker(&self, scheme: &str) -> Result<Arc<dyn Checker>> {
+		self
+			.checkers
+			.get(scheme)
+			.ok_or_else(|| Error::UnsupportedScheme(scheme.to_string()))
+	}
+	// every host to check: http hosts first, then tcp hosts (with CIDR blocks and port ranges
+	// expanded), then targets. A host that can't be expanded (see `validate`) is checked as is
+	pub fn all_targets(&self) -> Vec<Target> {
+		self
+			.supplied_targets()
+			.into_iter()
+			.flat_map(|target| {
+				target
+					.expand(self.max_expansion)
+					.unwrap_or_else(|_| vec![target])
+			})
+			.collect()
+	}
+	// the hosts as they were supplied, before expansion
+	fn supplied_targets(&self) -> Vec<Target> {
+		let http = self.http.iter().map(|spec| Target::http(spec));
+		let tcp = self.tcp.iter().map(|spec| Target::tcp(spec));
+		http
+			.chain(tcp)
+			.chain(self.targets.iter().cloned())
+			.collect()
+	}
+	// every problem with the hosts that can be found before anything is probed, see
+	// `validate_targets`
+	pub fn problems(&self) -> Vec<Error> {
+		let targets = self.supplied_targets();
+		let mut problems = validate_targets(&targets, &self.checkers, self.max_expansion);
+		// `family=` on a target can conflict with the source address too
+		for target in &targets {
+			let Some(family) = target.overrides.family else {
+				continue;
+			};
+			if let Err(e) = self.source.check_family(family) {
+				problems.push(Error::InvalidTarget(target.name.clone(), e.to_string()));
 			}
 		}
+		problems
+	}
+	// fail with every problem with the hosts at once
+	pub fn validate(&self) -> Result<()> {
+		Error::from_problems(self.problems())
 	}
-	pub async fn connection_report(self: &Self) -> ConnectionReport {
-		let mut result = ConnectionReport {
-			successful_hosts: vec![],
-			failed_hosts: vec![],
+	// the settings to check a target with, with its overrides applied
+	pub fn for_target(&self, target: &Target) -> Cow<'_, CanIConnect> {
+		if target.overrides.is_empty() {
+			return Cow::Borrowed(self);
+		}
+		let mut can_i_connect = self.clone();
+		target.overrides.apply(&mut can_i_connect);
+		Cow::Owned(can_i_connect)
+	}
+	pub async fn can_connect(&self, scheme: &str, host: &str) -> Result<ConnectionInfo> {
+		self.checker(scheme)?.check(self, host).await
+	}
+	// check every address the host resolves to. Fails with the first unreachable address
+	pub async fn can_connect_all(
+		&self,
+		scheme: &str,
+		host: &str,
+	) -> (Result<ConnectionInfo>, Vec<AddressResult>) {
+		let checker = match self.checker(scheme) {
+			Ok(checker) => checker,
+			Err(e) => return (Err(e), vec![]),
+		};
+		let authority = match checker.authority(host) {
+			Ok(authority) => authority,
+			Err(e) => return (Err(e), vec![]),
 		};
-		// check if http hosts are reachable
-		for url in self.http.clone() {
-			debug!("Trying URL: {}", url);
-			match self.can_connect(ConnectionType::HTTP, &url).await {
-				Ok(_) => {
-					result.successful_hosts.push(url.to_string());
-					info!("successfully connected to {}", url.to_string());
+		let addrs = match self.resolve(&authority).await {
+			Ok(addrs) => addrs,
+			Err(e) => return (Err(e), vec![]),
+		};
+		let checker = &checker;
+		let checks = addrs.iter().map(|addr| async move {
+			let start = Instant::now();
+			let connected = checker.connect(self, host, *addr).await;
+			(*addr, connected, start.elapsed())
+		});
+		let mut first_error = None;
+		let mut first_info = None;
+		let mut addresses = vec![];
+		for (addr, connected, latency) in futures::future::join_all(checks).await {
+			let mut address = AddressResult {
+				address: addr.ip(),
+				family: IpFamily::from(addr.ip()),
+				success: connected.is_ok(),
+				latency,
+				error_kind: None,
+				error: None,
+			};
+			match connected {
+				Ok(info) => {
+					first_info.get_or_insert(info);
 				}
 				Err(e) => {
-					result.failed_hosts.push(url.to_string());
-					error!("{}", e);
+					address.error_kind = Some(e.kind());
+					address.error = Some(e.to_string());
+					first_error.get_or_insert(Error::AddressFailed(addr.to_string(), Box::new(e)));
 				}
 			}
+			addresses.push(address);
 		}
-		// check if tcp hosts are reachable
-		for host in self.tcp.clone() {
-			match self.can_connect(ConnectionType::TCP, &host).await {
-				Ok(false) => {
-					result.failed_hosts.push(host.to_string());
-					error!("failed to connect to {}", host.to_string());
+		// report the details of the first address
+		let connected = match first_error {
+			Some(e) => Err(e),
+			None => Ok(first_info.unwrap_or_default()),
+		};
+		(connected, addresses)
+	}
+	// check a single target and record the details of how it went
+	pub async fn probe(&self, target: &Target) -> ProbeResult {
+		let scheme = target.scheme.as_str();
+		let host = &target.address;
+		let mut result = ProbeResult::for_target(target, &self.protocol(scheme));
+		loop {
+			result.attempts += 1;
+			let start = Instant::now();
+			let attempt = async {
+				match self.address_mode {
+					AddressMode::All => self.can_connect_all(scheme, host).await,
+					_ => (self.can_connect(scheme, host).await, vec![]),
 				}
-				Ok(true) => {
-					result.successful_hosts.push(host.to_string());
-					info!("successfully connected to {}", host.to_string());
+			};
+			// the total timeout bounds the whole attempt, whatever the checker does
+			let (connected, addresses) = time::timeout(self.timeouts.total, attempt)
+				.await
+				.unwrap_or_else(|_| (Err(Error::request_timed_out(self.timeouts.total)), vec![]));
+			result.latency = start.elapsed();
+			result.addresses = addresses;
+			match connected {
+				Ok(info) => {
+					result.status = ProbeStatus::Success;
+					result.success = true;
+					result.error_kind = None;
+					result.error = None;
+					result.resolved_ip = info.remote_addr.map(|addr| addr.ip());
+					result.local_ip = info.local_addr.map(|addr| addr.ip());
+					result.http_status = info.status;
+					result.port_state = info.port_state;
+					result.family = result.resolved_ip.map(IpFamily::from);
+					break;
 				}
 				Err(e) => {
-					result.failed_hosts.push(host.to_string());
-					error!("{}", e);
+					result.set_error(&e);
+					result.attempt_errors.push(AttemptError {
+						attempt: result.attempts,
+						error_kind: e.kind(),
+						error: e.to_string(),
+					});
+					if result.attempts >= self.retry.max_attempts() {
+						break;
+					}
+					let delay = self.retry.delay(result.attempts);
+					debug!(
+						"attempt {} to connect to {} failed ({}), retrying in {:?}",
+						result.attempts, host, e, delay
+					);
+					sleep(delay).await;
 				}
 			}
 		}
+		result.finished_at = SystemTime::now();
+		result
+	}
+	// connect to a host `sampling.samples` times, `sampling.interval` apart, and report latency
+	// statistics over every sample. The host is reachable if any sample connected; the details of the
+	// first successful sample (or the last failed one) are reported
+	pub async fn sample(&self, target: &Target) -> ProbeResult {
+		if self.sampling.samples <= 1 {
+			return self.probe(target).await;
+		}
+		let started_at = SystemTime::now();
+		let mut latencies = vec![];
+		let mut reported: Option<ProbeResult> = None;
+		for sample in 1..=self.sampling.samples {
+			if sample > 1 {
+				sleep(self.sampling.interval).await;
+			}
+			let result = self.probe(target).await;
+			debug!("sample {} of {}: {}", sample, self.sampling.samples, result);
+			latencies.push(result.success.then_some(result.latency));
+			match &reported {
+				Some(first) if first.success => {}
+				_ => reported = Some(result),
+			}
+		}
+		let mut result =
+			reported.unwrap_or_else(|| ProbeResult::for_target(target, &self.protocol(&target.scheme)));
+		result.stats = Some(LatencyStats::from_samples(&latencies));
+		result.started_at = started_at;
+		result.finished_at = SystemTime::now();
 		result
 	}
-	// return total hosts to check
-	pub fn hosts_total(self: &Self) -> usize {
-		self.http.len() + self.tcp.len()
+	// sample a target (with its overrides applied) and, for a must-fail target, expect the connection
+	// to fail
+	pub async fn check(&self, target: &Target) -> ProbeResult {
+		let mut result = self.for_target(target).sample(target).await;
+		if result.must_fail {
+			result.expect_blocked();
+		}
+		result
+	}
+	// probe every host concurrently (at most `concurrency` at a time) and report results in the
+	// order the hosts were supplied: http hosts first, then tcp hosts. Hosts still running when the
+	// deadline is reached are reported as cancelled. Dropping the returned future (e.g. when an HTTP
+	// client disconnects in server mode) aborts every in-flight probe and closes its sockets
+	pub async fn connection_report(&self) -> ConnectionReport {
+		self.report(None).await
+	}
+	// like `connection_report`, sending a `ProbeEvent` as every host starts and finishes and once the
+	// report is complete. Events are dropped if the receiver is
+	pub async fn connection_report_with_events(
+		&self,
+		events: UnboundedSender<ProbeEvent>,
+	) -> ConnectionReport {
+		self.report(Some(events)).await
+	}
+	// the events of a connection report, ending with `ReportComplete`. Dropping the stream aborts
+	// every in-flight probe
+	pub fn event_stream(self) -> impl Stream<Item = ProbeEvent> + Send + 'static {
+		let (sender, receiver) = mpsc::unbounded_channel();
+		// the report runs as the stream is polled, the channel closes when it completes
+		let report = stream::once(async move {
+			self.connection_report_with_events(sender).await;
+		})
+		.filter_map(|_| async { None });
+		let events = stream::unfold(receiver, |mut receiver| async move {
+			receiver.recv().await.map(|event| (event, receiver))
+		});
+		stream::select(events, report)
+	}
+	async fn report(&self, events: Option<UnboundedSender<ProbeEvent>>) -> ConnectionReport {
+		let started = Instant::now();
+		let hosts = self.all_targets();
+		let semaphore = Arc::new(Semaphore::new(self.concurrency.max(1)));
+		let can_i_connect = Arc::new(self.clone());
+		// tasks are aborted if the JoinSet is dropped before they finish
+		let mut probes = JoinSet::new();
+		for (index, target) in hosts.iter().cloned().enumerate() {
+			let semaphore = Arc::clone(&semaphore);
+			let can_i_connect = Arc::clone(&can_i_connect);
+			let events = events.clone();
+			probes.spawn(async move {
+				let _permit = semaphore.acquire_owned().await;
+				debug!("Trying {} host: {}", target.scheme, target);
+				send_event(
+					&events,
+					ProbeEvent::ProbeStarted {
+						index,
+						target: target.name.clone(),
+						protocol: can_i_connect.protocol(&target.scheme),
+						tag: target.tag.clone(),
+						started_at: SystemTime::now(),
+					},
+				);
+				(index, can_i_connect.check(&target).await)
+			});
+		}
+		// a deadline too far off to represent is no deadline
+		let deadline = self
+			.deadline
+			.and_then(|deadline| time::Instant::now().checked_add(deadline));
+		let mut deadline_reached = false;
+		let mut outcomes: Vec<Option<ProbeResult>> = vec![None; hosts.len()];
+		loop {
+			let joined = match deadline {
+				Some(deadline) => match time::timeout_at(deadline, probes.join_next()).await {
+					Ok(joined) => joined,
+					Err(_) => {
+						deadline_reached = true;
+						probes.abort_all();
+						break;
+					}
+				},
+				None => probes.join_next().await,
+			};
+			match joined {
+				Some(Ok((index, probe_result))) => {
+					log_result(&probe_result);
+					send_event(
+						&events,
+						ProbeEvent::ProbeFinished {
+							index,
+							result: Box::new(probe_result.clone()),
+						},
+					);
+					outcomes[index] = Some(probe_result);
+				}
+				Some(Err(e)) => error!("connection probe did not complete: {}", e),
+				None => break,
+			}
+		}
+		let results = hosts
+			.into_iter()
+			.zip(outcomes)
+			.enumerate()
+			.map(|(index, (target, outcome))| {
+				outcome.unwrap_or_else(|| {
+					let mut result = ProbeResult::for_target(&target, &self.protocol(&target.scheme));
+					if deadline_reached {
+						let deadline = self.deadline.unwrap_or_default();
+						result.set_error(&Error::DeadlineExceeded(
+							humantime::format_duration(deadline).to_string(),
+						));
+					} else {
+						result.error = Some(String::from("connection probe did not complete"));
+					}
+					log_result(&result);
+					send_event(
+						&events,
+						ProbeEvent::ProbeFinished {
+							index,
+							result: Box::new(result.clone()),
+						},
+					);
+					result
+				})
+			})
+			.collect::<Vec<ProbeResult>>();
+		let report = ConnectionReport { results };
+		send_event(
+			&events,
+			ProbeEvent::ReportComplete {
+				hosts_total: report.results.len(),
+				hosts_successful: report.successful_hosts().len(),
+				hosts_failed: report.failed_hosts().len(),
+				hosts_cancelled: report.cancelled_hosts().len(),
+				elapsed: started.elapsed(),
+			},
+		);
+		report
+	}
+	// return total hosts to check, after expanding CIDR blocks and port ranges
+	pub fn hosts_total(&self) -> usize {
+		self.all_targets().len()
 	}
 	// bind to SocketAddr (http server mode)
-	pub async fn bind(self: &Self) {
+	#[cfg(feature = "server")]
+	pub async fn bind(&self) {
 		info!("In Server Mode, listening on: {}", self.listen_addr);
 		// handler func
-		// setup routes
+		// setup routes, requests can not ask for more than the server was started with
+		let limits = ServerLimits {
+			max_expansion: self.max_expansion,
+			max_concurrency: self.concurrency,
+			..ServerLimits::default()
+		};
 		let routes_all = Router::new()
 			.merge(web::routes_health::routes())
-			.merge(web::routes_can_i_connect::routes())
+			.merge(web::routes_can_i_connect::routes(limits))
 			.route_layer(middleware::from_fn(track_metrics));
 		// start server
-		let addr = self.listen_addr
-      .parse::<SocketAddr>()
-      .expect(
-        &format!("Failed to parse listen address '{}'. Please ensure the format is 'IP:PORT' and have valid values", self.listen_addr)
-      );
+		let addr = self.listen_addr.parse::<SocketAddr>().unwrap_or_else(|_| {
+			panic!(
+				"Failed to parse listen address '{}'. Please ensure the format is 'IP:PORT' and have valid values",
+				self.listen_addr
+			)
+		});
 		debug!("Binding to: {addr}");
 		let listener = tokio::net::TcpListener::bind(&self.listen_addr)
 			.await
@@ -118,14 +810,429 @@ impl CanIConnect {
 	}
 }
 
+impl ProbeResult {
+	// an empty result for a target
+	pub fn for_target(target: &Target, protocol: &str) -> ProbeResult {
+		let mut result = ProbeResult::new(&target.name, protocol);
+		result.tag = target.tag.clone();
+		result.expanded_from = target.expanded_from.clone();
+		result.must_fail = target.must_fail;
+		result
+	}
+	pub fn new(target: &str, protocol: &str) -> ProbeResult {
+		let now = SystemTime::now();
+		ProbeResult {
+			target: target.to_string(),
+			protocol: protocol.to_string(),
+			tag: None,
+			expanded_from: None,
+			must_fail: false,
+			status: ProbeStatus::Failed,
+			success: false,
+			resolved_ip: None,
+			family: None,
+			local_ip: None,
+			http_status: None,
+			port_state: None,
+			latency: Duration::ZERO,
+			error_kind: None,
+			error: None,
+			blocked: None,
+			addresses: vec![],
+			attempts: 0,
+			attempt_errors: vec![],
+			stats: None,
+			started_at: now,
+			finished_at: now,
+		}
+	}
+	// turn the result of a must-fail host around: it passes when the connection failed
+	pub fn expect_blocked(&mut self) {
+		match self.status {
+			// a silent udp port is as likely dropped by a firewall as open
+			ProbeStatus::Success if self.port_state == Some(PortState::OpenFiltered) => {
+				self.status = ProbeStatus::Blocked;
+				self.blocked = Some(BlockKind::Dropped);
+			}
+			ProbeStatus::Success => {
+				self.status = ProbeStatus::UnexpectedlyReachable;
+				self.success = false;
+			}
+			ProbeStatus::Failed => {
+				self.status = ProbeStatus::Blocked;
+				self.success = true;
+				self.blocked = Some(self.error_kind.map_or(BlockKind::Other, BlockKind::from));
+			}
+			_ => {}
+		}
+	}
+	pub fn set_error(&mut self, e: &Error) {
+		self.status = match e.kind() {
+			ErrorKind::Cancelled => ProbeStatus::Cancelled,
+			_ => ProbeStatus::Failed,
+		};
+		self.success = false;
+		self.error_kind = Some(e.kind());
+		self.error = Some(e.to_string());
+	}
+}
+
+impl From<IpAddr> for IpFamily {
+	fn from(ip: IpAddr) -> IpFamily {
+		match ip {
+			IpAddr::V4(_) => IpFamily::Ipv4,
+			IpAddr::V6(_) => IpFamily::Ipv6,
+		}
+	}
+}
+
+impl From<ErrorKind> for BlockKind {
+	fn from(kind: ErrorKind) -> BlockKind {
+		match kind {
+			ErrorKind::Timeout => BlockKind::Dropped,
+			ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset | ErrorKind::NetworkUnreachable => {
+				BlockKind::Rejected
+			}
+			ErrorKind::DnsNxdomain
+			| ErrorKind::DnsNoRecords
+			| ErrorKind::NoAddressForFamily
+			| ErrorKind::DnsFailure => BlockKind::Unresolved,
+			_ => BlockKind::Other,
+		}
+	}
+}
+
+impl fmt::Display for ProbeStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ProbeStatus::Success => write!(f, "success"),
+			ProbeStatus::Failed => write!(f, "failed"),
+			ProbeStatus::Cancelled => write!(f, "cancelled"),
+			ProbeStatus::Blocked => write!(f, "blocked"),
+			ProbeStatus::UnexpectedlyReachable => write!(f, "unexpectedly reachable"),
+		}
+	}
+}
+
+impl fmt::Display for BlockKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BlockKind::Dropped => write!(f, "dropped"),
+			BlockKind::Rejected => write!(f, "rejected"),
+			BlockKind::Unresolved => write!(f, "unresolved"),
+			BlockKind::Other => write!(f, "other"),
+		}
+	}
+}
+
+impl fmt::Display for IpFamily {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			IpFamily::Ipv4 => write!(f, "IPv4"),
+			IpFamily::Ipv6 => write!(f, "IPv6"),
+		}
+	}
+}
+
+impl fmt::Display for ProbeResult {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.fmt_summary(f)?;
+		if let Some(stats) = &self.stats {
+			write!(f, "\n\t{}", stats)?;
+		}
+		for address in &self.addresses {
+			write!(f, "\n\t{}", address)?;
+		}
+		Ok(())
+	}
+}
+
+impl ProbeResult {
+	fn fmt_summary(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let protocol = &self.protocol;
+		let latency = self.latency.as_secs_f64() * 1000.0;
+		match self.status {
+			ProbeStatus::Success | ProbeStatus::UnexpectedlyReachable => {
+				match self.status {
+					ProbeStatus::Success => write!(f, "successfully connected to")?,
+					_ => write!(f, "unexpectedly reachable, connected to")?,
+				}
+				write!(f, " {} [{}]", self.target, protocol)?;
+				if let (Some(ip), Some(family)) = (self.resolved_ip, self.family) {
+					write!(f, " via {} ({})", ip, family)?;
+				}
+				if let Some(local_ip) = self.local_ip {
+					write!(f, " from {}", local_ip)?;
+				}
+				if self.port_state == Some(PortState::OpenFiltered) {
+					write!(f, " (open|filtered, no reply)")?;
+				}
+
+				write!(f, " in {:.1}ms", latency)?;
+				if self.attempts > 1 {
+					write!(f, " (after {} attempts)", self.attempts)?;
+				}
+				Ok(())
+			}
+			_ => {
+				match (self.status, self.blocked) {
+					(ProbeStatus::Blocked, Some(blocked)) => write!(
+						f,
+						"correctly blocked ({}) {} [{}] after {:.1}ms",
+						blocked, self.target, protocol, latency
+					)?,
+					_ => write!(
+						f,
+						"failed to connect to {} [{}] after {:.1}ms",
+						self.target, protocol, latency
+					)?,
+				}
+				if self.attempts > 1 {
+					write!(f, " and {} attempts", self.attempts)?;
+				}
+				if let Some(e) = &self.error {
+					let kind = self.error_kind.unwrap_or(ErrorKind::Internal);
+					write!(f, " ({}): {}", kind, e)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+impl fmt::Display for AddressResult {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let latency = self.latency.as_secs_f64() * 1000.0;
+		match (&self.error_kind, &self.error) {
+			(Some(kind), Some(e)) => write!(
+				f,
+				"{} [{}] unreachable after {:.1}ms ({}): {}",
+				self.address, self.family, latency, kind, e
+			),
+			_ => write!(
+				f,
+				"{} [{}] reachable in {:.1}ms",
+				self.address, self.family, latency
+			),
+		}
+	}
+}
+
+impl ConnectionReport {
+	// the whole report and how it measures up to the success policy, the `/can-i-connect` response
+	pub fn to_json(&self, success_policy: &SuccessPolicy) -> Value {
+		let failed_hosts = self.failed_hosts();
+		let successful_hosts = self.successful_hosts();
+		let cancelled_hosts = self.cancelled_hosts();
+		let blocked_hosts = self.blocked_hosts();
+		let unexpectedly_reachable = self.unexpectedly_reachable_hosts();
+		json!({
+			"success": was_successful(success_policy, &self.results),
+			"outcome": Outcome::from_results(success_policy, &self.results),
+			"success_policy": success_policy,
+			"connection_report": {
+				"failures": {
+					"hosts_unreachable": failed_hosts.len(),
+					"failed_hosts_list": failed_hosts,
+				},
+				"successful": {
+					"hosts_reachable": successful_hosts.len(),
+					"successful_hosts_list": successful_hosts,
+				},
+				"cancelled": {
+					"hosts_cancelled": cancelled_hosts.len(),
+					"cancelled_hosts_list": cancelled_hosts,
+				},
+				"correctly_blocked": {
+					"hosts_blocked": blocked_hosts.len(),
+					"blocked_hosts_list": blocked_hosts,
+				},
+				"unexpectedly_reachable": {
+					"hosts_unexpectedly_reachable": unexpectedly_reachable.len(),
+					"unexpectedly_reachable_hosts_list": unexpectedly_reachable,
+				},
+				"expanded": self.expansions(),
+				"results": self.results,
+			},
+		})
+	}
+	pub fn successful_hosts(&self) -> Vec<String> {
+		self.hosts_with(|result| result.success && !result.must_fail)
+	}
+	// derived view of the hosts that could not be reached, hosts cut off by the deadline are cancelled
+	// rather than failed
+	pub fn failed_hosts(&self) -> Vec<String> {
+		self.hosts_with(|result| {
+			!result.success && !result.must_fail && result.status != ProbeStatus::Cancelled
+		})
+	}
+	pub fn cancelled_hosts(&self) -> Vec<String> {
+		self.hosts_with(|result| result.status == ProbeStatus::Cancelled)
+	}
+	// must-fail hosts that could not be connected to
+	pub fn blocked_hosts(&self) -> Vec<String> {
+		self.hosts_with(|result| result.status == ProbeStatus::Blocked)
+	}
+	// must-fail hosts that could be connected to
+	pub fn unexpectedly_reachable_hosts(&self) -> Vec<String> {
+		self.hosts_with(|result| result.status == ProbeStatus::UnexpectedlyReachable)
+	}
+	pub fn failed_results(&self) -> Vec<&ProbeResult> {
+		self
+			.results
+			.iter()
+			.filter(|result| !result.success && !result.must_fail)
+			.collect()
+	}
+	// the results of every expanded CIDR block or port range, in the order they were supplied
+	pub fn expansions(&self) -> Vec<ExpansionSummary> {
+		let mut summaries: Vec<ExpansionSummary> = vec![];
+		for result in &self.results {
+			let Some(spec) = &result.expanded_from else {
+				continue;
+			};
+			let index = match summaries.iter().position(|summary| &summary.spec == spec) {
+				Some(index) => index,
+				None => {
+					summaries.push(ExpansionSummary {
+						spec: spec.clone(),
+						hosts_total: 0,
+						hosts_successful: 0,
+						failed_hosts_list: vec![],
+					});
+					summaries.len() - 1
+				}
+			};
+			let summary = &mut summaries[index];
+			summary.hosts_total += 1;
+			match result.success {
+				true => summary.hosts_successful += 1,
+				false => summary.failed_hosts_list.push(result.target.clone()),
+			}
+		}
+		summaries
+	}
+	fn hosts_with(&self, filter: impl Fn(&ProbeResult) -> bool) -> Vec<String> {
+		self
+			.results
+			.iter()
+			.filter(|result| filter(result))
+			.map(|result| result.target.clone())
+			.collect()
+	}
+}
+
 // endregion: methods
 
+// region: functions
+// results are logged as soon as each host finishes, so slow lists show progress
+fn log_result(result: &ProbeResult) {
+	if result.success {
+		info!("{}", result);
+	} else {
+		error!("{}", result);
+	}
+}
+
+fn send_event(events: &Option<UnboundedSender<ProbeEvent>>, event: ProbeEvent) {
+	if let Some(events) = events {
+		// nobody listening is not an error
+		let _ = events.send(event);
+	}
+}
+
+// endregion: functions
+
 // region: unit tests
 #[cfg(test)]
 pub mod unit_tests {
-	use crate::error::Result;
+	use super::{CanIConnect, DEFAULT_CONCURRENCY};
+	use crate::dns::AddressFamily;
+	use crate::error::{Error, Result};
+	use crate::timeouts::{Timeouts, DEFAULT_TIMEOUT};
+	use std::net::{IpAddr, Ipv4Addr};
+	use std::time::Duration;
 	#[test]
 	fn hello_test() -> Result<()> {
 		Ok(())
 	}
+
+	#[test]
+	fn builder_test() {
+		let can_i_connect = CanIConnect::builder()
+			.http_host("https://example.com")
+			.tcp_hosts(["db=example.com:5432", "!example.com:22"])
+			.deadline(Duration::from_secs(30))
+			.build()
+			.unwrap();
+		assert_eq!(can_i_connect.http, vec!["https://example.com"]);
+		assert_eq!(can_i_connect.hosts_total(), 3);
+		assert_eq!(can_i_connect.timeouts, Timeouts::new(DEFAULT_TIMEOUT));
+		assert_eq!(can_i_connect.concurrency, DEFAULT_CONCURRENCY);
+		assert_eq!(can_i_connect.deadline, Some(Duration::from_secs(30)));
+		assert!(!can_i_connect.server_mode);
+
+		assert!(matches!(
+			CanIConnect::builder().build(),
+			Err(Error::NoHostsSupplied)
+		));
+		assert!(matches!(
+			CanIConnect::builder()
+				.tcp_host("example.com:22")
+				.concurrency(0)
+				.build(),
+			Err(Error::InvalidConcurrency(_))
+		));
+
+		let can_i_connect = CanIConnect::builder()
+			.tcp_host("example.com:22")
+			.timeout(Duration::from_millis(1500))
+			.connect_timeout(Duration::from_millis(250))
+			.build()
+			.unwrap();
+		assert_eq!(can_i_connect.timeouts.total, Duration::from_millis(1500));
+		assert_eq!(can_i_connect.timeouts.connect(), Duration::from_millis(250));
+		assert_eq!(can_i_connect.timeouts.tls(), Duration::from_millis(1500));
+	}
+
+	#[test]
+	fn source_family_conflict_test() {
+		let source = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5));
+		assert!(matches!(
+			CanIConnect::builder()
+				.tcp_host("example.com:22")
+				.source_address(source)
+				.family(AddressFamily::Ipv6Only)
+				.build(),
+			Err(Error::SourceFamilyConflict(..))
+		));
+		assert!(matches!(
+			CanIConnect::builder()
+				.target("tcp://example.com:22?family=ipv6-only".parse().unwrap())
+				.source_address(source)
+				.build(),
+			Err(Error::InvalidTargets(problems)) if problems.len() == 1
+		));
+		// a preference is narrowed to the source address family
+		assert!(CanIConnect::builder()
+			.tcp_host("example.com:22")
+			.source_address(source)
+			.family(AddressFamily::PreferIpv6)
+			.build()
+			.is_ok());
+	}
+
+	// server mode needs no hosts up front, they come with each request
+	#[cfg(feature = "server")]
+	#[test]
+	fn builder_listen_test() {
+		assert!(matches!(
+			CanIConnect::builder().listen("localhost").build(),
+			Err(Error::InvalidSocketAddr(_))
+		));
+		assert!(CanIConnect::builder()
+			.listen("127.0.0.1:8000")
+			.build()
+			.is_ok());
+	}
 }
@@ -0,0 +1,398 @@
+use crate::can_i_connect::{AddressMode, CanIConnect};
+use crate::dns::AddressFamily;
+use crate::error::{Error, Result};
+use crate::helpers::{
+	authority_host, connect_tcp, exchange_udp, handle_http, handle_http_via, handle_tcp,
+	handshake_tls, handshake_tls_over, happy_eyeballs, http_authority, interleave_families, race_tcp,
+};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+
+// region: enums
+// what a udp check learned about the port, UDP has no handshake to confirm it is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PortState {
+	// the service replied
+	#[serde(rename = "open")]
+	Open,
+	// nothing came back, not even an ICMP port unreachable: the service ignored the datagram or a
+	// firewall dropped it
+	#[serde(rename = "open|filtered")]
+	OpenFiltered,
+}
+
+// endregion: enums
+
+// region: structs
+// details about an established connection
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionInfo {
+	pub remote_addr: Option<SocketAddr>,
+	// the local address the connection was made from
+	pub local_addr: Option<SocketAddr>,
+	// the response status of HTTP checks
+	pub status: Option<u16>,
+	// the state of the port of UDP checks
+	pub port_state: Option<PortState>,
+}
+
+// checkers keyed by the URL scheme of the targets they handle
+#[derive(Clone)]
+pub struct CheckerRegistry {
+	checkers: HashMap<String, Arc<dyn Checker>>,
+}
+
+// checks `http://` and `https://` URLs by sending a GET request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpChecker;
+
+// checks `<host>:<port>` targets by opening a TCP connection
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpChecker;
+
+// checks `<host>:<port>` targets by completing a TLS handshake, the certificate must be valid for the
+// host
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TlsChecker;
+
+// checks `<host>:<port>` targets by sending a UDP datagram. Passes unless the port is closed, see
+// `PortState`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UdpChecker;
+
+// endregion: structs
+
+// region: traits
+// a way of checking whether a target can be connected to. Register an implementation with
+// `CheckerRegistry::register` (or `CanIConnectBuilder::checker`) to support a new URL scheme
+#[async_trait]
+pub trait Checker: Send + Sync {
+	// reported as the `protocol` of every result e.g. `http`
+	fn protocol(&self) -> &'static str;
+	// the `<host>:<port>` the target connects to, used to resolve its addresses
+	fn authority(&self, target: &str) -> Result<String>;
+	// connect to the target through a single, already resolved, address
+	async fn connect(
+		&self,
+		can_i_connect: &CanIConnect,
+		target: &str,
+		addr: SocketAddr,
+	) -> Result<ConnectionInfo>;
+	// finish the check over the connection that won the Happy Eyeballs race. By default it is closed
+	// and `connect` connects to the winning address again
+	async fn connect_over(
+		&self,
+		can_i_connect: &CanIConnect,
+		target: &str,
+		addr: SocketAddr,
+		stream: TcpStream,
+	) -> Result<ConnectionInfo> {
+		drop(stream);
+		self.connect(can_i_connect, target, addr).await
+	}
+	// connect to the target through the first address it resolves to (or the winner of the Happy
+	// Eyeballs race)
+	async fn check(&self, can_i_connect: &CanIConnect, target: &str) -> Result<ConnectionInfo> {
+		resolve_and_connect(self, can_i_connect, target).await
+	}
+}
+
+// endregion: traits
+
+// region: methods
+impl CheckerRegistry {
+	// a registry without any checkers, see `Default` for the built in ones
+	pub fn new() -> CheckerRegistry {
+		CheckerRegistry {
+			checkers: HashMap::new(),
+		}
+	}
+	// handle targets with the given URL scheme with `checker`, replacing any previous checker
+	pub fn register(&mut self, scheme: &str, checker: impl Checker + 'static) {
+		self
+			.checkers
+			.insert(scheme.to_lowercase(), Arc::new(checker));
+	}
+	pub fn get(&self, scheme: &str) -> Option<Arc<dyn Checker>> {
+		self.checkers.get(&scheme.to_lowercase()).cloned()
+	}
+	pub fn schemes(&self) -> Vec<String> {
+		let mut schemes = self.checkers.keys().cloned().collect::<Vec<String>>();
+		schemes.sort();
+		schemes
+	}
+}
+
+impl Default for CheckerRegistry {
+	fn default() -> CheckerRegistry {
+		let mut registry = CheckerRegistry::new();
+		registry.register("http", HttpChecker);
+		registry.register("https", HttpChecker);
+		registry.register("tcp", TcpChecker);
+		registry.register("tls", TlsChecker);
+		registry.register("udp", UdpChecker);
+		registry
+	}
+}
+
+impl fmt::Debug for CheckerRegistry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.schemes()).finish()
+	}
+}
+
+#[async_trait]
+impl Checker for HttpChecker {
+	fn protocol(&self) -> &'static str {
+		"http"
+	}
+	fn authority(&self, target: &str) -> Result<String> {
+		http_authority(target).map(|(_, authority)| authority)
+	}
+	async fn connect(
+		&self,
+		can_i_connect: &CanIConnect,
+		target: &str,
+		addr: SocketAddr,
+	) -> Result<ConnectionInfo> {
+		let (domain, _) = http_authority(target)?;
+		let info = handle_http_via(
+			&target.to_string(),
+			&domain,
+			&[addr],
+			&can_i_connect.timeouts,
+			&can_i_connect.source,
+		)
+		.await?;
+		expect_status(can_i_connect, target, info)
+	}
+	async fn check(&self, can_i_connect: &CanIConnect, target: &str) -> Result<ConnectionInfo> {
+		// a supplied client picks the address itself and is used as is, unless the family was restricted
+		// (or a source address set), IPv6 is preferred or a resolver was supplied. Otherwise the
+		// addresses are resolved and ordered by family here and the request is pinned to the one picked
+		if let Some(client) = &can_i_connect.http_client {
+			if can_i_connect.address_mode == AddressMode::First
+				&& can_i_connect.family() == AddressFamily::PreferIpv4
+				&& can_i_connect.resolver.is_none()
+			{
+				let info = handle_http(
+					&target.to_string(),
+					Some(client),
+					&can_i_connect.timeouts,
+					&can_i_connect.source,
+				)
+				.await?;
+				return expect_status(can_i_connect, target, info);
+			}
+		}
+		if can_i_connect.address_mode == AddressMode::HappyEyeballs {
+			// the HTTP client races the addresses itself, so the request goes over the winning
+			// connection instead of a second one
+			let (domain, authority) = http_authority(target)?;
+			let addrs = interleave_families(can_i_connect.resolve(&authority).await?);
+			let info = handle_http_via(
+				&target.to_string(),
+				&domain,
+				&addrs,
+				&can_i_connect.timeouts,
+				&can_i_connect.source,
+			)
+			.await?;
+			return expect_status(can_i_connect, target, info);
+		}
+		resolve_and_connect(self, can_i_connect, target).await
+	}
+}
+
+#[async_trait]
+impl Checker for TcpChecker {
+	fn protocol(&self) -> &'static str {
+		"tcp"
+	}
+	fn authority(&self, target: &str) -> Result<String> {
+		Ok(target.to_string())
+	}
+	async fn connect(
+		&self,
+		can_i_connect: &CanIConnect,
+		_target: &str,
+		addr: SocketAddr,
+	) -> Result<ConnectionInfo> {
+		connect_tcp(
+			addr,
+			can_i_connect.timeouts.connect(),
+			&can_i_connect.source,
+		)
+		.await
+	}
+	async fn check(&self, can_i_connect: &CanIConnect, target: &str) -> Result<ConnectionInfo> {
+		let timeout = can_i_connect.timeouts.connect();
+		match can_i_connect.address_mode {
+			// the winning connection is the check, no need to connect again
+			AddressMode::HappyEyeballs => {
+				let addrs = can_i_connect.resolve(target).await?;
+				happy_eyeballs(addrs, timeout, &can_i_connect.source).await
+			}
+			_ => handle_tcp(
+				can_i_connect.resolver(),
+				&target.to_string(),
+				timeout,
+				can_i_connect.family(),
+				&can_i_connect.source,
+			)
+			.await
+			.map_err(|e| can_i_connect.source.family_error(target, e)),
+		}
+	}
+}
+
+#[async_trait]
+impl Checker for TlsChecker {
+	fn protocol(&self) -> &'static str {
+		"tls"
+	}
+	fn authority(&self, target: &str) -> Result<String> {
+		Ok(target.to_string())
+	}
+	async fn connect(
+		&self,
+		can_i_connect: &CanIConnect,
+		target: &str,
+		addr: SocketAddr,
+	) -> Result<ConnectionInfo> {
+		handshake_tls(
+			authority_host(target),
+			addr,
+			&can_i_connect.timeouts,
+			&can_i_connect.source,
+		)
+		.await
+	}
+	async fn connect_over(
+		&self,
+		can_i_connect: &CanIConnect,
+		target: &str,
+		_addr: SocketAddr,
+		stream: TcpStream,
+	) -> Result<ConnectionInfo> {
+		handshake_tls_over(authority_host(target), stream, &can_i_connect.timeouts).await
+	}
+}
+
+#[async_trait]
+impl Checker for UdpChecker {
+	fn protocol(&self) -> &'static str {
+		"udp"
+	}
+	fn authority(&self, target: &str) -> Result<String> {
+		Ok(target.to_string())
+	}
+	async fn connect(
+		&self,
+		can_i_connect: &CanIConnect,
+		_target: &str,
+		addr: SocketAddr,
+	) -> Result<ConnectionInfo> {
+		exchange_udp(
+			addr,
+			can_i_connect.timeouts.udp_reply(),
+			&can_i_connect.source,
+		)
+		.await
+	}
+	async fn check(&self, can_i_connect: &CanIConnect, target: &str) -> Result<ConnectionInfo> {
+		// there is no connection to race, Happy Eyeballs only applies to TCP
+		let addrs = can_i_connect.resolve(target).await?;
+		match addrs.first() {
+			Some(addr) => self.connect(can_i_connect, target, *addr).await,
+			None => Err(Error::DnsNoRecords(target.to_string())),
+		}
+	}
+}
+
+// endregion: methods
+
+// region: functions
+// fail the check when the response status is not one of the expected statuses
+fn expect_status(
+	can_i_connect: &CanIConnect,
+	target: &str,
+	info: ConnectionInfo,
+) -> Result<ConnectionInfo> {
+	match (&can_i_connect.expect_status, info.status) {
+		(Some(expected), Some(status)) if !expected.matches(status) => Err(Error::UnexpectedStatus(
+			target.to_string(),
+			status,
+			expected.to_string(),
+		)),
+		_ => Ok(info),
+	}
+}
+
+// the scheme of a `<scheme>://...` target
+pub fn target_scheme(target: &str) -> Option<&str> {
+	target
+		.split_once("://")
+		.map(|(scheme, _)| scheme)
+		.filter(|scheme| !scheme.is_empty())
+}
+
+// resolve the target (keeping to the address family) and connect through the first address, or
+// over the connection that wins the Happy Eyeballs race
+pub async fn resolve_and_connect<C: Checker + ?Sized>(
+	checker: &C,
+	can_i_connect: &CanIConnect,
+	target: &str,
+) -> Result<ConnectionInfo> {
+	let authority = checker.authority(target)?;
+	let timeout = can_i_connect.timeouts.connect();
+	let addrs = can_i_connect.resolve(&authority).await?;
+	if can_i_connect.address_mode == AddressMode::HappyEyeballs {
+		let (addr, stream) = race_tcp(addrs, timeout, &can_i_connect.source).await?;
+		return checker
+			.connect_over(can_i_connect, target, addr, stream)
+			.await;
+	}
+	match addrs.first() {
+		Some(addr) => checker.connect(can_i_connect, target, *addr).await,
+		None => Err(Error::DnsNoRecords(authority)),
+	}
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::{target_scheme, CheckerRegistry};
+
+	#[test]
+	fn checker_registry_test() {
+		let registry = CheckerRegistry::default();
+		assert_eq!(
+			registry.schemes(),
+			vec!["http", "https", "tcp", "tls", "udp"]
+		);
+		assert_eq!(registry.get("HTTPS").unwrap().protocol(), "http");
+		assert_eq!(registry.get("tcp").unwrap().protocol(), "tcp");
+		assert_eq!(registry.get("udp").unwrap().protocol(), "udp");
+		assert!(registry.get("ldap").is_none());
+		assert_eq!(
+			format!("{:?}", registry),
+			r#"{"http", "https", "tcp", "tls", "udp"}"#
+		);
+	}
+
+	#[test]
+	fn target_scheme_test() {
+		assert_eq!(target_scheme("https://example.com/"), Some("https"));
+		assert_eq!(target_scheme("example.com:443"), None);
+		assert_eq!(target_scheme("://example.com"), None);
+	}
+}
+// endregion: unit tests
@@ -1,19 +1,103 @@
 use crate::error::Error;
-use std::net::{SocketAddr, ToSocketAddrs};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use tokio::net::lookup_host;
 
 // This is the trait we'll use for DNS resolution
-pub trait DnsResolver {
-	fn resolve(&self, host: &str) -> Result<Vec<SocketAddr>, Error>;
+#[async_trait]
+pub trait DnsResolver: Send + Sync {
+	async fn resolve(&self, host: &str) -> Result<Vec<SocketAddr>, Error>;
 }
 
-// The default implementation that does the actual DNS resolution
+impl fmt::Debug for dyn DnsResolver {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "DnsResolver")
+	}
+}
+
+// which IP family to connect over when a host resolves to both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressFamily {
+	// use IPv4 when available, fall back to IPv6
+	#[default]
+	PreferIpv4,
+	// use IPv6 when available, fall back to IPv4
+	PreferIpv6,
+	// never connect over IPv6
+	#[serde(alias = "ipv4")]
+	Ipv4Only,
+	// never connect over IPv4
+	#[serde(alias = "ipv6")]
+	Ipv6Only,
+}
+
+impl AddressFamily {
+	// drop the addresses this family does not allow and put the preferred ones first
+	pub fn apply(&self, addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+		let (v4, v6): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.into_iter().partition(|a| a.is_ipv4());
+		match self {
+			AddressFamily::PreferIpv4 => v4.into_iter().chain(v6).collect(),
+			AddressFamily::PreferIpv6 => v6.into_iter().chain(v4).collect(),
+			AddressFamily::Ipv4Only => v4,
+			AddressFamily::Ipv6Only => v6,
+		}
+	}
+}
+
+impl fmt::Display for AddressFamily {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			AddressFamily::PreferIpv4 => write!(f, "prefer-ipv4"),
+			AddressFamily::PreferIpv6 => write!(f, "prefer-ipv6"),
+			AddressFamily::Ipv4Only => write!(f, "ipv4-only"),
+			AddressFamily::Ipv6Only => write!(f, "ipv6-only"),
+		}
+	}
+}
+
+impl FromStr for AddressFamily {
+	type Err = Error;
+
+	fn from_str(family: &str) -> Result<AddressFamily, Error> {
+		match family.trim().to_lowercase().as_str() {
+			"prefer-ipv4" => Ok(AddressFamily::PreferIpv4),
+			"prefer-ipv6" => Ok(AddressFamily::PreferIpv6),
+			"ipv4-only" | "ipv4" => Ok(AddressFamily::Ipv4Only),
+			"ipv6-only" | "ipv6" => Ok(AddressFamily::Ipv6Only),
+			_ => Err(Error::InvalidAddressFamily(family.to_string())),
+		}
+	}
+}
+
+// The default implementation that does the actual DNS resolution (without blocking the runtime)
 pub struct DefaultResolver;
 
+#[async_trait]
 impl DnsResolver for DefaultResolver {
-	fn resolve(&self, host: &str) -> Result<Vec<SocketAddr>, Error> {
-		host
-			.to_socket_addrs()
+	async fn resolve(&self, host: &str) -> Result<Vec<SocketAddr>, Error> {
+		lookup_host(host)
+			.await
 			.map(|iter| iter.collect())
-			.map_err(|_| Error::DNSResolutionFailed(host.to_string()))
+			.map_err(|e| dns_error(host, &e))
+	}
+}
+
+// Turn a resolver error into an error that says why the lookup failed
+pub fn dns_error(host: &str, e: &io::Error) -> Error {
+	let message = e.to_string().to_lowercase();
+	if message.contains("no address associated") || message.contains("no data") {
+		Error::DnsNoRecords(host.to_string())
+	} else if message.contains("name or service not known")
+		|| message.contains("nodename nor servname")
+		|| message.contains("no such host")
+	{
+		Error::DnsNxDomain(host.to_string())
+	} else {
+		Error::DNSResolutionFailed(host.to_string())
 	}
 }
@@ -1,10 +1,16 @@
+#[cfg(feature = "server")]
 use axum::http::StatusCode;
+#[cfg(feature = "server")]
 use axum::response::{IntoResponse, Response};
+#[cfg(feature = "cli")]
 use clap::Error as clap_error;
 use derive_more::{Display, From};
 use reqwest::Error as req_err;
+use serde::Serialize;
 use std::error::Error as StdError;
+use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -13,13 +19,96 @@ pub enum Error {
 	// -- Internals
 	#[display("--log-level must be a one of [info|error|debug] but got {}", _0)]
 	InvalidLogLevel(String),
-	#[display("--timeout must be a number but got {}", _0)]
-	InvalidTimeout(String),
-	#[display("request took longer than {} seconds", _0)]
-	RequestTimedOut(usize),
-	#[display("No hosts supplied. Must supply hosts through --http-hosts or --tcp-hosts args. Both cannot be empty!")]
+	#[display("--concurrency must be a number greater than 0 but got {}", _0)]
+	InvalidConcurrency(String),
+	#[display("--retries must be a number but got {}", _0)]
+	InvalidRetries(String),
+	#[display("--samples must be a number greater than 0 but got {}", _0)]
+	InvalidSamples(String),
+	#[display("{} must be a duration e.g. 500ms, 1.5s or 2m but got {}", _0, _1)]
+	InvalidDuration(String, String),
+	#[display(
+		"family must be one of [prefer-ipv4|prefer-ipv6|ipv4-only|ipv6-only] but got {}",
+		_0
+	)]
+	InvalidAddressFamily(String),
+	#[display("{} is not a valid target: {}", _0, _1)]
+	InvalidTarget(String, String),
+	#[display("found {} problem(s) with the hosts:\n{}", _0.len(), list_errors(_0))]
+	InvalidTargets(Vec<Error>),
+	#[display("--max-expansion must be a number greater than 0 but got {}", _0)]
+	InvalidMaxExpansion(String),
+	#[display(
+		"{} expands to more than {} targets, raise --max-expansion to check them all",
+		_0,
+		_1
+	)]
+	ExpansionTooLarge(String, usize),
+	#[display("--output must be one of [log|json|junit|tap|table] but got {}", _0)]
+	InvalidOutputFormat(String),
+	#[display("--output-file needs a report format e.g. --output junit")]
+	OutputFileWithoutFormat,
+	#[display("could not write the report to {}: {}", _0, _1)]
+	ReportWriteFailed(String, String),
+	#[display("--source-address must be an IP address but got {}", _0)]
+	InvalidSourceAddress(String),
+	#[display(
+		"source address {} cannot connect over {}, the address families conflict",
+		_0,
+		_1
+	)]
+	SourceFamilyConflict(String, String),
+	#[display(
+		"--success-policy must be one of [all|any|<N>|<N>%|group:<tag>=<N>,...] but got {}",
+		_0
+	)]
+	InvalidSuccessPolicy(String),
+	#[display("request took longer than {}", _0)]
+	RequestTimedOut(String),
+	#[display("connection was not established within {}", _0)]
+	ConnectTimedOut(String),
+	#[display("TLS handshake did not complete within {}", _0)]
+	TlsTimedOut(String),
+	#[display("No hosts supplied. Must supply hosts through --targets, --http-hosts or --tcp-hosts args. They cannot all be empty!")]
 	NoHostsSupplied,
+	#[display("could not resolve DNS for host: {}", _0)]
 	DNSResolutionFailed(String),
+	#[display("host {} does not exist (NXDOMAIN)", _0)]
+	DnsNxDomain(String),
+	#[display("host {} has no address records", _0)]
+	DnsNoRecords(String),
+	#[display("host {} has no address records allowed by {}", _0, _1)]
+	NoAddressForFamily(String, String),
+	#[display("connection to {} was refused", _0)]
+	ConnectionRefused(String),
+	#[display("connection to {} was reset", _0)]
+	ConnectionReset(String),
+	#[display("network is unreachable for {}", _0)]
+	NetworkUnreachable(String),
+	#[display("TLS handshake with {} failed: {}", _0, _1)]
+	TlsHandshakeFailed(String, String),
+	#[display("proxy error while connecting to {}: {}", _0, _1)]
+	ProxyError(String, String),
+	#[display("failed to connect to {}: {}", _0, _1)]
+	ConnectionFailed(String, String),
+	#[display("could not bind to {}: {}", _0, _1)]
+	SourceBindFailed(String, String),
+	#[display(
+		"cannot reach {} from source address {}, the address families differ",
+		_1,
+		_0
+	)]
+	SourceFamilyMismatch(String, String),
+	#[display("{} responded with status {}, expected {}", _0, _1, _2)]
+	UnexpectedStatus(String, u16, String),
+	#[display("{} ({})", _1, _0)]
+	AddressFailed(String, Box<Error>),
+	#[display("no checker is registered for {}:// targets", _0)]
+	UnsupportedScheme(String),
+	#[display("{} is not a valid URL", _0)]
+	InvalidUrl(String),
+	#[display("cancelled, the report deadline of {} was reached", _0)]
+	DeadlineExceeded(String),
 	#[display(
 		"{} is not a valid bind address, use format <interface>:<port> e.g. 127.0.0.1:8000",
 		_0
@@ -27,14 +116,116 @@ pub enum Error {
 	InvalidSocketAddr(String),
 
 	// -- Externals
+	#[cfg(feature = "cli")]
 	#[from]
 	Clap(Arc<clap_error>),
 	#[from]
 	ReqwestError(Arc<req_err>),
 }
 
+// category of a connection failure, serialized as a stable machine readable code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+	DnsNxdomain,
+	DnsNoRecords,
+	// the host resolves, but not to an address of the family the check is restricted to
+	NoAddressForFamily,
+	DnsFailure,
+	ConnectionRefused,
+	ConnectionReset,
+	Timeout,
+	NetworkUnreachable,
+	Tls,
+	Proxy,
+	Connection,
+	Http,
+	Cancelled,
+	Internal,
+}
+
+impl ErrorKind {
+	pub fn code(&self) -> &'static str {
+		match self {
+			ErrorKind::DnsNxdomain => "dns_nxdomain",
+			ErrorKind::DnsNoRecords => "dns_no_records",
+			ErrorKind::NoAddressForFamily => "no_address_for_family",
+			ErrorKind::DnsFailure => "dns_failure",
+			ErrorKind::ConnectionRefused => "connection_refused",
+			ErrorKind::ConnectionReset => "connection_reset",
+			ErrorKind::Timeout => "timeout",
+			ErrorKind::NetworkUnreachable => "network_unreachable",
+			ErrorKind::Tls => "tls",
+			ErrorKind::Proxy => "proxy",
+			ErrorKind::Connection => "connection",
+			ErrorKind::Http => "http",
+			ErrorKind::Cancelled => "cancelled",
+			ErrorKind::Internal => "internal",
+		}
+	}
+}
+
+impl fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.code())
+	}
+}
+
+impl Error {
+	pub fn request_timed_out(timeout: Duration) -> Error {
+		Error::RequestTimedOut(humantime::format_duration(timeout).to_string())
+	}
+	pub fn connect_timed_out(timeout: Duration) -> Error {
+		Error::ConnectTimedOut(humantime::format_duration(timeout).to_string())
+	}
+	pub fn tls_timed_out(timeout: Duration) -> Error {
+		Error::TlsTimedOut(humantime::format_duration(timeout).to_string())
+	}
+	// fail with every problem found at once, if there were any
+	pub fn from_problems(problems: Vec<Error>) -> Result<()> {
+		match problems.is_empty() {
+			true => Ok(()),
+			false => Err(Error::InvalidTargets(problems)),
+		}
+	}
+	// failure category used in reports
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Error::RequestTimedOut(_) | Error::ConnectTimedOut(_) | Error::TlsTimedOut(_) => {
+				ErrorKind::Timeout
+			}
+			Error::DNSResolutionFailed(_) => ErrorKind::DnsFailure,
+			Error::DnsNxDomain(_) => ErrorKind::DnsNxdomain,
+			Error::DnsNoRecords(_) => ErrorKind::DnsNoRecords,
+			Error::NoAddressForFamily(..) => ErrorKind::NoAddressForFamily,
+			Error::ConnectionRefused(_) => ErrorKind::ConnectionRefused,
+			Error::ConnectionReset(_) => ErrorKind::ConnectionReset,
+			Error::NetworkUnreachable(_) => ErrorKind::NetworkUnreachable,
+			Error::TlsHandshakeFailed(..) => ErrorKind::Tls,
+			Error::ProxyError(..) => ErrorKind::Proxy,
+			Error::ConnectionFailed(..)
+			| Error::SourceBindFailed(..)
+			| Error::SourceFamilyMismatch(..) => ErrorKind::Connection,
+			Error::ReqwestError(_) | Error::UnexpectedStatus(..) => ErrorKind::Http,
+			Error::DeadlineExceeded(_) => ErrorKind::Cancelled,
+			Error::AddressFailed(_, e) => e.kind(),
+			_ => ErrorKind::Internal,
+		}
+	}
+}
+
 impl StdError for Error {}
 
+// one error per line, for errors that hold several others
+fn list_errors(errors: &[Error]) -> String {
+	errors
+		.iter()
+		.map(|e| format!("  - {}", e))
+		.collect::<Vec<String>>()
+		.join("\n")
+}
+
+#[cfg(feature = "server")]
 impl IntoResponse for Error {
 	fn into_response(self) -> Response {
 		println!("->> {:<4} - {self:?}", "INTO_RES");
@@ -1,25 +1,54 @@
 use crate::{
-	dns::{DefaultResolver, DnsResolver},
+	can_i_connect::{IpFamily, ProbeResult},
+	checker::{ConnectionInfo, PortState},
+	dns::{dns_error, AddressFamily, DnsResolver},
 	error::{Error, Result},
+	policy::SuccessPolicy,
+	source::Source,
+	timeouts::Timeouts,
 };
+#[cfg(feature = "cli")]
 use ansi_term::Colour;
+#[cfg(feature = "cli")]
 use env_logger::{Builder, Target};
-use log::{debug, error, warn, Level, LevelFilter, Record};
+use hyper_util::client::legacy::connect::HttpInfo;
+use log::{debug, error, warn};
+#[cfg(feature = "cli")]
+use log::{Level, LevelFilter, Record};
 use reqwest::Client;
+use serde::Serializer;
+#[cfg(feature = "cli")]
+use std::str::FromStr;
 use std::{
-	io::Write,
-	net::{SocketAddr, SocketAddrV6, TcpStream},
-	str::FromStr,
+	error::Error as StdError,
+	io,
+	net::SocketAddr,
+	result::Result as StdResult,
 	sync::Arc,
-	time::Duration,
+	time::{Duration, SystemTime},
 };
+use tokio::{net::TcpStream, task::JoinSet, time};
+
+// region: constants
+// RFC 8305 "Connection Attempt Delay" between starting connection attempts
+pub const HAPPY_EYEBALLS_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+// endregion: constants
+
+// region: structs
+
+// endregion: structs
 
 // region: functions
+#[cfg(feature = "cli")]
 pub fn parse_log_level(level: &String) -> Result<LevelFilter> {
 	LevelFilter::from_str(level).map_err(|_| Error::InvalidLogLevel(level.to_string()))
 }
 
+#[cfg(feature = "cli")]
 pub fn create_logger(with_color: bool) -> Builder {
+	use std::io::Write;
+
 	let mut builder = Builder::new();
 	builder.target(Target::Stdout);
 
@@ -51,97 +80,580 @@ pub fn create_logger(with_color: bool) -> Builder {
 				record.args()
 			)
 		};
-		result.map_err(|e| e.into())
+		result
 	});
 
 	builder // Return the owned Builder instance
 }
 
-// Returns the first ip address the given host resolves to (prefers IPV4, falls back to IPV6)
-pub fn get_address(resolver: &dyn DnsResolver, host: &str) -> Result<Option<SocketAddr>> {
-	debug!("Attempting to resolve dns for address: {}", host);
-	let addrs = resolver.resolve(host)?;
-	let mut ipv6_fallback: Option<SocketAddrV6> = None;
-	for addr in addrs {
-		match addr {
-			SocketAddr::V4(ipv4_addr) => {
-				debug!("{} resolved to {} (IPv4)", host, ipv4_addr);
-				return Ok(Some(SocketAddr::V4(ipv4_addr)));
-			}
-			SocketAddr::V6(ipv6_addr) => {
-				if ipv6_fallback.is_none() {
-					ipv6_fallback = Some(ipv6_addr);
-				}
-			}
+// columns of the terminal stdout is attached to, `COLUMNS` when the terminal can't be asked
+#[cfg(feature = "cli")]
+pub fn terminal_width() -> Option<usize> {
+	#[cfg(unix)]
+	{
+		let mut size = libc::winsize {
+			ws_row: 0,
+			ws_col: 0,
+			ws_xpixel: 0,
+			ws_ypixel: 0,
+		};
+		// SAFETY: TIOCGWINSZ only writes into the winsize it is given
+		let found = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+		if found && size.ws_col > 0 {
+			return Some(size.ws_col as usize);
 		}
 	}
-	if let Some(ipv6_addr) = ipv6_fallback {
-		debug!(
-			"{} did not resolve to any IPv4 addresses, falling back to IPv6: {}",
-			host, ipv6_addr
-		);
-		return Ok(Some(SocketAddr::V6(ipv6_addr)));
+	std::env::var("COLUMNS")
+		.ok()?
+		.parse::<usize>()
+		.ok()
+		.filter(|width| *width > 0)
+}
+
+// name of the machine the report is made on
+pub fn hostname() -> String {
+	#[cfg(unix)]
+	{
+		let mut name = [0u8; 256];
+		// SAFETY: gethostname writes at most `name.len()` bytes into the buffer it is given
+		let found =
+			unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) } == 0;
+		let end = name
+			.iter()
+			.position(|byte| *byte == 0)
+			.unwrap_or(name.len());
+		if found && end > 0 {
+			return String::from_utf8_lossy(&name[..end]).into_owned();
+		}
+	}
+	std::env::var("HOSTNAME")
+		.or_else(|_| std::env::var("COMPUTERNAME"))
+		.unwrap_or_else(|_| String::from("unknown"))
+}
+
+// Returns the first ip address the given host resolves to, in the order the address family prefers
+pub async fn get_address(
+	resolver: &dyn DnsResolver,
+	host: &str,
+	family: AddressFamily,
+) -> Result<Option<SocketAddr>> {
+	debug!("Attempting to resolve dns for address: {}", host);
+	let addrs = resolver.resolve(host).await?;
+	let address = family.apply(addrs).into_iter().next();
+	match address {
+		Some(addr) => debug!(
+			"{} resolved to {} ({})",
+			host,
+			addr,
+			IpFamily::from(addr.ip())
+		),
+		None => debug!(
+			"{} did not resolve to any addresses allowed by {}",
+			host, family
+		),
 	}
-	debug!("{} did not resolve to any IPv4 addresses", host);
-	Ok(None) // No addresses found
+	Ok(address)
 }
 
-pub async fn handle_http(host: &String, client: Option<&Client>, timeout: usize) -> Result<bool> {
-	let c = Client::default();
-	let client = client.unwrap_or_else(|| &c);
+pub async fn handle_http(
+	host: &String,
+	client: Option<&Client>,
+	timeouts: &Timeouts,
+	source: &Source,
+) -> Result<ConnectionInfo> {
+	let client = match client {
+		Some(client) => client.clone(),
+		None => build_http_client(host, timeouts, source, None)?,
+	};
 	let resp = client.get(host).send().await;
 	match resp {
 		Ok(r) => {
 			debug!("Request to {} got Response code: {}", host, r.status());
-			Ok(true)
+			// the connector records both ends of the connection the request went over
+			let info = r.extensions().get::<HttpInfo>();
+			Ok(ConnectionInfo {
+				remote_addr: info.map(|info| info.remote_addr()),
+				local_addr: info.map(|info| info.local_addr()),
+				status: Some(r.status().as_u16()),
+				..Default::default()
+			})
 		}
 		Err(e) => {
 			error!("HTTP Error: {}", e);
-			if e.is_timeout() {
-				Err(Error::RequestTimedOut(timeout))
-			} else {
-				Err(Error::ReqwestError(Arc::new(e)))
-			}
+			Err(classify_reqwest_error(host, e, timeouts))
+		}
+	}
+}
+
+// an HTTP client that keeps to the connect, TLS and total timeouts and connects from the source.
+// Requests for `domain` are sent to the given addresses instead of whatever the domain resolves to,
+// the client races them (Happy Eyeballs) when there are several
+pub fn build_http_client(
+	url: &str,
+	timeouts: &Timeouts,
+	source: &Source,
+	resolve: Option<(&str, &[SocketAddr])>,
+) -> Result<Client> {
+	let mut builder = Client::builder()
+		.timeout(timeouts.total)
+		.connect_timeout(timeouts.http_connect(is_https(url)))
+		.local_address(source.address);
+	if let Some(interface) = &source.interface {
+		#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+		{
+			builder = builder.interface(interface);
 		}
+		#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+		return Err(Error::SourceBindFailed(
+			interface.to_string(),
+			String::from("binding to an interface is only supported on Linux"),
+		));
+	}
+	if let Some((domain, addrs)) = resolve {
+		builder = builder.resolve_to_addrs(domain, addrs);
 	}
+	builder
+		.build()
+		.map_err(|e| Error::ReqwestError(Arc::new(e)))
+}
+
+fn is_https(url: &str) -> bool {
+	url
+		.get(..8)
+		.is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://"))
 }
 
-pub async fn handle_tcp(host: &String, timeout: usize) -> Result<bool> {
-	let timeout = Duration::from_secs(timeout as u64);
-	let resolver = DefaultResolver;
-	match get_address(&resolver, host) {
-		Ok(Some(addr)) => Ok(TcpStream::connect_timeout(&addr, timeout).is_ok()),
+pub async fn handle_tcp(
+	resolver: &dyn DnsResolver,
+	host: &String,
+	timeout: Duration,
+	family: AddressFamily,
+	source: &Source,
+) -> Result<ConnectionInfo> {
+	let address = match time::timeout(timeout, get_address(resolver, host, family)).await {
+		Ok(address) => address,
+		Err(_) => {
+			warn!("Timed out resolving DNS for host: {}", host);
+			return Err(Error::connect_timed_out(timeout));
+		}
+	};
+	match address {
+		Ok(Some(addr)) => connect_tcp(addr, timeout, source).await,
 		Ok(None) => {
 			warn!("Could not resolve DNS for host: {}", host);
-			Ok(false)
+			Err(Error::NoAddressForFamily(
+				host.to_string(),
+				family.to_string(),
+			))
 		}
 		Err(e) => {
-			error!(
-				"Error getting IPv4 address for host: {} : {}",
-				host,
-				e.to_string()
+			error!("Error getting address for host: {} : {}", host, e);
+			Err(e)
+		}
+	}
+}
+
+// open a TCP connection to a single, already resolved, address
+pub async fn connect_tcp(
+	addr: SocketAddr,
+	timeout: Duration,
+	source: &Source,
+) -> Result<ConnectionInfo> {
+	let stream = open_tcp(addr, timeout, source).await?;
+	Ok(ConnectionInfo {
+		remote_addr: stream.peer_addr().ok().or(Some(addr)),
+		local_addr: stream.local_addr().ok(),
+		..Default::default()
+	})
+}
+
+async fn open_tcp(addr: SocketAddr, timeout: Duration, source: &Source) -> Result<TcpStream> {
+	let socket = source.tcp_socket(addr)?;
+	match time::timeout(timeout, socket.connect(addr)).await {
+		Ok(Ok(stream)) => Ok(stream),
+		Ok(Err(e)) => {
+			debug!("Failed to connect to {}: {}", addr, e);
+			Err(classify_io_error(&addr.to_string(), &e, timeout))
+		}
+		Err(_) => {
+			debug!("Timed out connecting to {}", addr);
+			Err(Error::connect_timed_out(timeout))
+		}
+	}
+}
+
+// open a TCP connection to a single address and complete a TLS handshake over it, verifying the
+// certificate is valid for `domain`
+pub async fn handshake_tls(
+	domain: &str,
+	addr: SocketAddr,
+	timeouts: &Timeouts,
+	source: &Source,
+) -> Result<ConnectionInfo> {
+	let stream = open_tcp(addr, timeouts.connect(), source).await?;
+	handshake_tls_over(domain, stream, timeouts).await
+}
+
+// complete a TLS handshake over a connection that is already open
+pub async fn handshake_tls_over(
+	domain: &str,
+	stream: TcpStream,
+	timeouts: &Timeouts,
+) -> Result<ConnectionInfo> {
+	let remote_addr = stream.peer_addr().ok();
+	let local_addr = stream.local_addr().ok();
+	let connector = native_tls::TlsConnector::new()
+		.map_err(|e| Error::TlsHandshakeFailed(domain.to_string(), e.to_string()))?;
+	let connector = tokio_native_tls::TlsConnector::from(connector);
+	match time::timeout(timeouts.tls(), connector.connect(domain, stream)).await {
+		Ok(Ok(_)) => Ok(ConnectionInfo {
+			remote_addr,
+			local_addr,
+			..Default::default()
+		}),
+		Ok(Err(e)) => {
+			debug!(
+				"TLS handshake with {} ({:?}) failed: {}",
+				domain, remote_addr, e
 			);
-			Ok(false)
+			Err(Error::TlsHandshakeFailed(domain.to_string(), e.to_string()))
 		}
+		Err(_) => Err(Error::tls_timed_out(timeouts.tls())),
 	}
 }
 
-pub fn validate_bind_addr(addr: &String) -> Result<SocketAddr> {
+// send an empty datagram and wait for any reply. UDP has no handshake: a reply means the port is
+// open and an ICMP port unreachable that it is closed (refused). A service that ignores the datagram
+// can't be told apart from a firewall that drops it, so silence is reported as open|filtered
+pub async fn exchange_udp(
+	addr: SocketAddr,
+	timeout: Duration,
+	source: &Source,
+) -> Result<ConnectionInfo> {
+	let socket = source.udp_socket(addr).await?;
+	let exchange = async {
+		socket.connect(addr).await?;
+		socket.send(&[]).await?;
+		let mut reply = [0; 512];
+		socket.recv(&mut reply).await
+	};
+	let port_state = match time::timeout(timeout, exchange).await {
+		Ok(Ok(_)) => PortState::Open,
+		Ok(Err(e)) => {
+			debug!("UDP exchange with {} failed: {}", addr, e);
+			return Err(classify_io_error(&addr.to_string(), &e, timeout));
+		}
+		Err(_) => {
+			debug!("no reply from {} within {:?}, open|filtered", addr, timeout);
+			PortState::OpenFiltered
+		}
+	};
+	Ok(ConnectionInfo {
+		remote_addr: Some(addr),
+		local_addr: socket.local_addr().ok(),
+		port_state: Some(port_state),
+		..Default::default()
+	})
+}
+
+// the host name of a `<host>:<port>` authority, without the brackets around IPv6 addresses
+pub fn authority_host(authority: &str) -> &str {
+	let host = match authority.rsplit_once(':') {
+		Some((host, port)) if port.parse::<u16>().is_ok() => host,
+		_ => authority,
+	};
+	host.trim_start_matches('[').trim_end_matches(']')
+}
+
+// send the HTTP request to a specific address instead of whatever the host name resolves to first
+pub async fn handle_http_via(
+	url: &String,
+	domain: &str,
+	addrs: &[SocketAddr],
+	timeouts: &Timeouts,
+	source: &Source,
+) -> Result<ConnectionInfo> {
+	let client = build_http_client(url, timeouts, source, Some((domain, addrs)))?;
+	handle_http(url, Some(&client), timeouts, source).await
+}
+
+// Returns every address the given host resolves to that the address family allows
+pub async fn resolve_all(
+	resolver: &dyn DnsResolver,
+	host: &str,
+	timeout: Duration,
+	family: AddressFamily,
+) -> Result<Vec<SocketAddr>> {
+	debug!("Attempting to resolve all addresses for: {}", host);
+	match time::timeout(timeout, resolver.resolve(host)).await {
+		Ok(Ok(addrs)) if addrs.is_empty() => Err(Error::DnsNoRecords(host.to_string())),
+		Ok(Ok(addrs)) => match family.apply(addrs) {
+			allowed if allowed.is_empty() => Err(Error::NoAddressForFamily(
+				host.to_string(),
+				family.to_string(),
+			)),
+			allowed => Ok(allowed),
+		},
+		Ok(Err(e)) => Err(e),
+		Err(_) => Err(Error::connect_timed_out(timeout)),
+	}
+}
+
+// split a URL into the host name and the `<host>:<port>` it connects to
+pub fn http_authority(url: &str) -> Result<(String, String)> {
+	let parsed = reqwest::Url::parse(url).map_err(|_| Error::InvalidUrl(url.to_string()))?;
+	match (parsed.host_str(), parsed.port_or_known_default()) {
+		(Some(host), Some(port)) => Ok((host.to_string(), format!("{}:{}", host, port))),
+		_ => Err(Error::InvalidUrl(url.to_string())),
+	}
+}
+
+// order addresses the way RFC 8305 suggests: alternate between families, starting with IPv6
+pub fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+	let (v6, v4): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.into_iter().partition(|a| a.is_ipv6());
+	let mut v6 = v6.into_iter();
+	let mut v4 = v4.into_iter();
+	let mut ordered = vec![];
+	loop {
+		match (v6.next(), v4.next()) {
+			(None, None) => break,
+			(a, b) => ordered.extend(a.into_iter().chain(b)),
+		}
+	}
+	ordered
+}
+
+// race connection attempts (RFC 8305 "Happy Eyeballs"), starting a new attempt every
+// HAPPY_EYEBALLS_ATTEMPT_DELAY or as soon as the previous one fails. The first address to connect wins
+pub async fn happy_eyeballs(
+	addrs: Vec<SocketAddr>,
+	timeout: Duration,
+	source: &Source,
+) -> Result<ConnectionInfo> {
+	let (addr, stream) = race_tcp(addrs, timeout, source).await?;
+	Ok(ConnectionInfo {
+		remote_addr: Some(addr),
+		local_addr: stream.local_addr().ok(),
+		..Default::default()
+	})
+}
+
+// the Happy Eyeballs race, handing over the winning connection
+pub async fn race_tcp(
+	addrs: Vec<SocketAddr>,
+	timeout: Duration,
+	source: &Source,
+) -> Result<(SocketAddr, TcpStream)> {
+	let mut pending = interleave_families(addrs).into_iter().peekable();
+	let mut attempts = JoinSet::new();
+	let mut last_error = None;
+	let race = async {
+		loop {
+			if let Some(addr) = pending.next() {
+				debug!("Happy Eyeballs: trying {}", addr);
+				let socket = source.tcp_socket(addr);
+				attempts.spawn(async move {
+					let connected = match socket {
+						Ok(socket) => socket
+							.connect(addr)
+							.await
+							.map_err(|e| classify_io_error(&addr.to_string(), &e, timeout)),
+						Err(e) => Err(e),
+					};
+					(addr, connected)
+				});
+			}
+			if attempts.is_empty() {
+				break;
+			}
+			let stagger = time::sleep(HAPPY_EYEBALLS_ATTEMPT_DELAY);
+			tokio::select! {
+				Some(joined) = attempts.join_next() => match joined {
+					Ok((addr, Ok(stream))) => {
+						debug!("Happy Eyeballs: {} won", addr);
+						return Ok((addr, stream));
+					}
+					Ok((_, Err(e))) => last_error = Some(e),
+					Err(e) => error!("Happy Eyeballs attempt did not complete: {}", e),
+				},
+				_ = stagger, if pending.peek().is_some() => {}
+			}
+		}
+		Err(last_error.unwrap_or(Error::DnsNoRecords(String::from(
+			"no addresses to connect to",
+		))))
+	};
+	time::timeout(timeout, race)
+		.await
+		.unwrap_or(Err(Error::connect_timed_out(timeout)))
+}
+
+// Map a socket level error onto the failure category it represents
+pub fn classify_io_error(target: &str, e: &io::Error, timeout: Duration) -> Error {
+	let target = target.to_string();
+	match e.kind() {
+		io::ErrorKind::ConnectionRefused => Error::ConnectionRefused(target),
+		io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => {
+			Error::ConnectionReset(target)
+		}
+		io::ErrorKind::TimedOut => Error::connect_timed_out(timeout),
+		io::ErrorKind::NetworkUnreachable | io::ErrorKind::HostUnreachable => {
+			Error::NetworkUnreachable(target)
+		}
+		_ => Error::ConnectionFailed(target, e.to_string()),
+	}
+}
+
+// Walk the error chain of a failed HTTP request to figure out why it failed
+pub fn classify_reqwest_error(host: &str, e: reqwest::Error, timeouts: &Timeouts) -> Error {
+	let connect_timeout = timeouts.http_connect(is_https(host));
+	if e.is_timeout() && e.is_connect() {
+		return Error::connect_timed_out(connect_timeout);
+	} else if e.is_timeout() {
+		return Error::request_timed_out(timeouts.total);
+	}
+	let mut messages = vec![];
+	let mut source = e.source();
+	while let Some(err) = source {
+		if let Some(io_err) = err.downcast_ref::<io::Error>() {
+			// getaddrinfo failures surface as io errors with the resolver message
+			if messages
+				.iter()
+				.any(|message: &String| message.contains("dns error"))
+			{
+				return dns_error(host, io_err);
+			}
+			match classify_io_error(host, io_err, connect_timeout) {
+				Error::ConnectionFailed(..) => {}
+				classified => return classified,
+			}
+		}
+		messages.push(err.to_string().to_lowercase());
+		source = err.source();
+	}
+	let chain = messages.join(": ");
+	if chain.contains("proxy") || chain.contains("tunnel") {
+		Error::ProxyError(host.to_string(), chain)
+	} else if chain.contains("certificate")
+		|| chain.contains("ssl")
+		|| chain.contains("tls")
+		|| chain.contains("handshake")
+	{
+		Error::TlsHandshakeFailed(host.to_string(), chain)
+	} else if chain.contains("dns error") {
+		Error::DNSResolutionFailed(host.to_string())
+	} else if e.is_connect() {
+		Error::ConnectionFailed(host.to_string(), chain)
+	} else {
+		Error::ReqwestError(Arc::new(e))
+	}
+}
+
+pub fn validate_bind_addr(addr: &str) -> Result<SocketAddr> {
 	match addr.parse::<SocketAddr>() {
 		Ok(socket_addr) => Ok(socket_addr),
-		Err(_) => Err(Error::InvalidSocketAddr(addr.clone())),
+		Err(_) => Err(Error::InvalidSocketAddr(addr.to_string())),
 	}
 }
 
+#[cfg(feature = "server")]
 pub fn handler_log(path: &str) -> String {
-	return format!("->> {:<4} - handler_health - {path}", "HANDLER");
+	format!("->> {:<4} - handler_health - {path}", "HANDLER")
+}
+
+// parse a duration such as `250ms`, `1.5s`, `2m` or `1m 30s`. Bare numbers are seconds
+pub fn parse_duration(value: &str) -> Option<Duration> {
+	let value = value.trim();
+	if let Ok(secs) = value.parse::<f64>() {
+		return Duration::try_from_secs_f64(secs).ok();
+	}
+	if let Ok(duration) = humantime::parse_duration(value) {
+		return Some(duration);
+	}
+	// humantime does not handle fractions e.g. `1.5s`
+	let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+	let (number, unit) = value.split_at(split);
+	let number = number.parse::<f64>().ok()?;
+	let unit_secs = match unit.trim() {
+		"ms" => 0.001,
+		"s" | "sec" | "secs" => 1.0,
+		"m" | "min" | "mins" => 60.0,
+		"h" | "hr" | "hrs" => 3600.0,
+		_ => return None,
+	};
+	Duration::try_from_secs_f64(number * unit_secs).ok()
+}
+
+// serialize a Duration as fractional milliseconds
+pub fn serialize_duration_ms<S>(duration: &Duration, serializer: S) -> StdResult<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+// serialize an optional Duration as (fractional) milliseconds, or null
+pub fn serialize_optional_duration_ms<S>(
+	duration: &Option<Duration>,
+	serializer: S,
+) -> StdResult<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	match duration {
+		Some(duration) => serialize_duration_ms(duration, serializer),
+		None => serializer.serialize_none(),
+	}
+}
+
+// serialize a SystemTime as an RFC 3339 timestamp
+pub fn serialize_timestamp<S>(time: &SystemTime, serializer: S) -> StdResult<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	serializer.collect_str(&humantime::format_rfc3339_millis(*time))
 }
 
-pub fn was_successful(failed_hosts: Vec<String>) -> bool {
-	if failed_hosts.len() > 0 {
-		return false;
+// evaluate the success policy against the results of a connection report
+pub fn was_successful(policy: &SuccessPolicy, results: &[ProbeResult]) -> bool {
+	let reachable = results.iter().filter(|result| result.success).count();
+	match policy {
+		SuccessPolicy::All => reachable == results.len(),
+		SuccessPolicy::Any => reachable > 0,
+		SuccessPolicy::AtLeast(min) => reachable >= *min,
+		SuccessPolicy::Percent(percent) => {
+			results.is_empty() || (reachable as f64 / results.len() as f64) * 100.0 >= *percent
+		}
+		SuccessPolicy::PerGroup(groups) => groups.iter().all(|(tag, min)| {
+			let reachable_in_group = results
+				.iter()
+				.filter(|result| result.success && result.tag.as_deref() == Some(tag.as_str()))
+				.count();
+			reachable_in_group >= *min
+		}),
+	}
+}
+
+// split an optional `<tag>=` prefix off a host e.g. `db=postgres:5432`
+pub fn split_tag(host: &str) -> (Option<String>, String) {
+	if let Some((tag, rest)) = host.split_once('=') {
+		let is_tag = !tag.is_empty()
+			&& tag
+				.chars()
+				.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+		if is_tag {
+			return (Some(tag.to_string()), rest.to_string());
+		}
+	}
+	(None, host.to_string())
+}
+
+// split the `!` prefix that marks a host as must-fail off a host
+pub fn split_must_fail(host: &str) -> (bool, String) {
+	match host.trim_start().strip_prefix('!') {
+		Some(host) => (true, host.trim_start().to_string()),
+		None => (false, host.to_string()),
 	}
-	return true;
 }
 
 // endregion: functions
@@ -149,33 +661,46 @@ pub fn was_successful(failed_hosts: Vec<String>) -> bool {
 // region: unit tests
 #[cfg(test)]
 pub mod unit_tests {
+	#[cfg(feature = "server")]
+	use super::handler_log;
+	#[cfg(feature = "cli")]
+	use super::parse_log_level;
+	use super::{
+		classify_io_error, get_address, http_authority, interleave_families, parse_duration,
+		resolve_all, split_must_fail, split_tag, validate_bind_addr,
+	};
+	use crate::dns::{dns_error, AddressFamily, DnsResolver};
+	use crate::error::{Error, ErrorKind};
+	use async_trait::async_trait;
+	#[cfg(feature = "cli")]
 	use log::LevelFilter;
-
-	use super::{get_address, handler_log, parse_log_level, validate_bind_addr};
-	use crate::dns::DnsResolver;
-	use crate::error::Error;
+	use std::io;
 	use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+	use std::time::Duration;
 
 	// Setup Mock DNS Resolver
 	struct MockResolver {
 		addrs: Vec<SocketAddr>,
 	}
 
+	#[async_trait]
 	impl DnsResolver for MockResolver {
-		fn resolve(&self, _host: &str) -> Result<Vec<SocketAddr>, Error> {
+		async fn resolve(&self, _host: &str) -> Result<Vec<SocketAddr>, Error> {
 			Ok(self.addrs.clone())
 		}
 	}
 
-	#[test]
-	fn get_address_ipv4_test() {
+	#[tokio::test]
+	async fn get_address_ipv4_test() {
 		let resolver = MockResolver {
 			addrs: vec![SocketAddr::V4(SocketAddrV4::new(
 				Ipv4Addr::new(127, 0, 0, 1),
 				8000,
 			))],
 		};
-		let result = get_address(&resolver, "localhost").unwrap();
+		let result = get_address(&resolver, "localhost", AddressFamily::default())
+			.await
+			.unwrap();
 		assert_eq!(
 			result,
 			Some(SocketAddr::V4(SocketAddrV4::new(
@@ -185,8 +710,8 @@ pub mod unit_tests {
 		);
 	}
 
-	#[test]
-	fn get_address_ipv6_test() {
+	#[tokio::test]
+	async fn get_address_ipv6_test() {
 		let resolver = MockResolver {
 			addrs: vec![SocketAddr::V6(SocketAddrV6::new(
 				"::1".parse().unwrap(),
@@ -195,7 +720,9 @@ pub mod unit_tests {
 				0,
 			))],
 		};
-		let result = get_address(&resolver, "localhost").unwrap();
+		let result = get_address(&resolver, "localhost", AddressFamily::default())
+			.await
+			.unwrap();
 		assert_eq!(
 			result,
 			Some(SocketAddr::V6(SocketAddrV6::new(
@@ -207,13 +734,54 @@ pub mod unit_tests {
 		)
 	}
 
-	#[test]
-	fn get_address_no_addresses() {
+	#[tokio::test]
+	async fn get_address_no_addresses() {
 		let resolver = MockResolver { addrs: vec![] };
-		let result = get_address(&resolver, "localhost").unwrap();
+		let result = get_address(&resolver, "localhost", AddressFamily::default())
+			.await
+			.unwrap();
 		assert_eq!(result, None);
 	}
 
+	#[tokio::test]
+	async fn get_address_family_test() {
+		let v4: SocketAddr = "127.0.0.1:8000".parse().unwrap();
+		let v6: SocketAddr = "[::1]:8000".parse().unwrap();
+		let resolver = MockResolver {
+			addrs: vec![v6, v4],
+		};
+		let address = |family| get_address(&resolver, "localhost", family);
+		assert_eq!(address(AddressFamily::PreferIpv4).await.unwrap(), Some(v4));
+		assert_eq!(address(AddressFamily::PreferIpv6).await.unwrap(), Some(v6));
+		assert_eq!(address(AddressFamily::Ipv4Only).await.unwrap(), Some(v4));
+		assert_eq!(address(AddressFamily::Ipv6Only).await.unwrap(), Some(v6));
+
+		let v4_only = MockResolver { addrs: vec![v4] };
+		assert_eq!(
+			get_address(&v4_only, "localhost", AddressFamily::PreferIpv6)
+				.await
+				.unwrap(),
+			Some(v4)
+		);
+		assert_eq!(
+			get_address(&v4_only, "localhost", AddressFamily::Ipv6Only)
+				.await
+				.unwrap(),
+			None
+		);
+		match resolve_all(
+			&v4_only,
+			"localhost",
+			Duration::from_secs(1),
+			AddressFamily::Ipv6Only,
+		)
+		.await
+		{
+			Err(e) => assert_eq!(e.kind(), ErrorKind::NoAddressForFamily),
+			Ok(addrs) => panic!("expected no IPv6 addresses but got {:?}", addrs),
+		}
+	}
+
 	#[test]
 	fn validate_bind_addr_test() {
 		let valid_addr = String::from("127.0.0.1:8000");
@@ -224,7 +792,7 @@ pub mod unit_tests {
 				valid_addr.clone(),
 				"{} is a valid address and it should match the return but we get [{}] instead",
 				valid_addr,
-				addr.to_string()
+				addr
 			),
 			Err(e) => panic!("{}", e),
 		}
@@ -232,6 +800,7 @@ pub mod unit_tests {
 		let invalid_result = validate_bind_addr(&invalid_addr);
 		assert!(invalid_result.is_err(), "expected error but got OK");
 	}
+	#[cfg(feature = "cli")]
 	#[test]
 	fn parse_log_level_test() {
 		let valid_debug_log_levels = vec![
@@ -243,7 +812,7 @@ pub mod unit_tests {
 			let result = parse_log_level(&level);
 			match result {
 				Ok(log_level) => assert_eq!(log_level, LevelFilter::Debug),
-				Err(e) => panic!("did not expect to get error but got: [{}]", e.to_string()),
+				Err(e) => panic!("did not expect to get error but got: [{}]", e),
 			}
 		}
 		let valid_error_log_levels = vec![
@@ -255,7 +824,7 @@ pub mod unit_tests {
 			let result = parse_log_level(&level);
 			match result {
 				Ok(log_level) => assert_eq!(log_level, LevelFilter::Error),
-				Err(e) => panic!("did not expect to get error but got: [{}]", e.to_string()),
+				Err(e) => panic!("did not expect to get error but got: [{}]", e),
 			}
 		}
 		let invalid_log_levels = vec![String::from("critical")];
@@ -265,6 +834,137 @@ pub mod unit_tests {
 		}
 	}
 
+	#[test]
+	fn classify_io_error_test() {
+		let cases = vec![
+			(
+				io::ErrorKind::ConnectionRefused,
+				ErrorKind::ConnectionRefused,
+			),
+			(io::ErrorKind::ConnectionReset, ErrorKind::ConnectionReset),
+			(io::ErrorKind::ConnectionAborted, ErrorKind::ConnectionReset),
+			(io::ErrorKind::TimedOut, ErrorKind::Timeout),
+			(
+				io::ErrorKind::NetworkUnreachable,
+				ErrorKind::NetworkUnreachable,
+			),
+			(
+				io::ErrorKind::HostUnreachable,
+				ErrorKind::NetworkUnreachable,
+			),
+			(io::ErrorKind::Other, ErrorKind::Connection),
+		];
+		for (io_kind, expected) in cases {
+			let e = io::Error::new(io_kind, "boom");
+			assert_eq!(
+				classify_io_error("example.com:80", &e, Duration::from_secs(5)).kind(),
+				expected,
+				"unexpected classification for {:?}",
+				io_kind
+			);
+		}
+	}
+
+	#[test]
+	fn dns_error_test() {
+		let nxdomain =
+			io::Error::other("failed to lookup address information: Name or service not known");
+		assert_eq!(
+			dns_error("nope.invalid", &nxdomain).kind(),
+			ErrorKind::DnsNxdomain
+		);
+		let no_records =
+			io::Error::other("failed to lookup address information: No address associated with hostname");
+		assert_eq!(
+			dns_error("empty.example", &no_records).kind(),
+			ErrorKind::DnsNoRecords
+		);
+		let temporary = io::Error::other(
+			"failed to lookup address information: Temporary failure in name resolution",
+		);
+		assert_eq!(
+			dns_error("example.com", &temporary).kind(),
+			ErrorKind::DnsFailure
+		);
+		assert_eq!(ErrorKind::DnsNxdomain.code(), "dns_nxdomain");
+	}
+
+	#[test]
+	fn split_tag_test() {
+		assert_eq!(
+			split_tag("db=postgres:5432"),
+			(Some(String::from("db")), String::from("postgres:5432"))
+		);
+		assert_eq!(
+			split_tag("https://example.com/?a=b"),
+			(None, String::from("https://example.com/?a=b"))
+		);
+		assert_eq!(
+			split_tag("example.com:80"),
+			(None, String::from("example.com:80"))
+		);
+	}
+
+	#[test]
+	fn split_must_fail_test() {
+		assert_eq!(
+			split_must_fail("!example.com:22"),
+			(true, String::from("example.com:22"))
+		);
+		assert_eq!(
+			split_must_fail("!egress=example.com:22"),
+			(true, String::from("egress=example.com:22"))
+		);
+		assert_eq!(
+			split_must_fail("example.com:22"),
+			(false, String::from("example.com:22"))
+		);
+	}
+
+	#[test]
+	fn parse_duration_test() {
+		assert_eq!(parse_duration("5"), Some(Duration::from_secs(5)));
+		assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+		assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+		assert_eq!(parse_duration("1m 30s"), Some(Duration::from_secs(90)));
+		assert_eq!(parse_duration("0.5"), Some(Duration::from_millis(500)));
+		assert_eq!(parse_duration("soon"), None);
+		assert_eq!(parse_duration("-1"), None);
+		assert_eq!(parse_duration("1.5 fortnights"), None);
+	}
+
+	#[test]
+	fn interleave_families_test() {
+		let v4_a: SocketAddr = "10.0.0.1:80".parse().unwrap();
+		let v4_b: SocketAddr = "10.0.0.2:80".parse().unwrap();
+		let v4_c: SocketAddr = "10.0.0.3:80".parse().unwrap();
+		let v6_a: SocketAddr = "[2001:db8::1]:80".parse().unwrap();
+		let v6_b: SocketAddr = "[2001:db8::2]:80".parse().unwrap();
+		assert_eq!(
+			interleave_families(vec![v4_a, v4_b, v4_c, v6_a, v6_b]),
+			vec![v6_a, v4_a, v6_b, v4_b, v4_c]
+		);
+		assert_eq!(interleave_families(vec![v4_a, v4_b]), vec![v4_a, v4_b]);
+	}
+
+	#[test]
+	fn http_authority_test() {
+		assert_eq!(
+			http_authority("https://example.com/health").unwrap(),
+			(String::from("example.com"), String::from("example.com:443"))
+		);
+		assert_eq!(
+			http_authority("http://127.0.0.1:8000").unwrap(),
+			(String::from("127.0.0.1"), String::from("127.0.0.1:8000"))
+		);
+		assert_eq!(
+			http_authority("http://[::1]:8000/").unwrap(),
+			(String::from("[::1]"), String::from("[::1]:8000"))
+		);
+		assert!(http_authority("example.com:80").is_err());
+	}
+
+	#[cfg(feature = "server")]
 	#[test]
 	fn handler_log_test() {
 		let path = "/health";
@@ -1,23 +1,85 @@
 #[cfg(test)]
 pub mod integration {
 	use crate::{
-		can_i_connect::{CanIConnect, ConnectionType},
-		error::Error,
-		web::routes_can_i_connect::can_i_connect_handler,
+		can_i_connect::{AddressMode, BlockKind, CanIConnect, IpFamily, ProbeEvent, ProbeStatus},
+		checker::{Checker, ConnectionInfo, PortState},
+		dns::{AddressFamily, DefaultResolver, DnsResolver},
+		error::{Error, ErrorKind},
+		helpers::{handle_http, handle_tcp, happy_eyeballs},
+		output::{tap, ReportOutput},
+		policy::{Outcome, SuccessPolicy},
+		retry::RetryPolicy,
+		source::Source,
+		target::Target,
+		timeouts::Timeouts,
+		wait::wait_for,
+		web::{
+			route_helpers::build_can_i_connect,
+			routes_can_i_connect::{self, can_i_connect_handler},
+			routes_health,
+			types::{CanIConnectPayload, ServerLimits, MAX_SAMPLES},
+		},
 	};
-	use axum::{extract::OriginalUri, http::Uri, response::IntoResponse, Json};
+	use async_trait::async_trait;
+	use axum::{
+		extract::{OriginalUri, State},
+		http::{header, HeaderMap, HeaderValue, Uri},
+		response::IntoResponse,
+		Json,
+	};
+	use futures::StreamExt;
 	use http_body_util::BodyExt;
 	use httpmock::prelude::*;
 	use reqwest::{Client, StatusCode};
 	use serde_json::{json, Value};
-	use std::time::Duration;
-	use tokio;
+	use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::Arc;
+	use std::time::{Duration, Instant};
+	use tokio::{
+		self,
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::{TcpListener, TcpSocket, TcpStream},
+		sync::mpsc,
+	};
 
 	// region: Functions
 	fn create_server() -> MockServer {
 		MockServer::start()
 	}
 
+	// returns a listener whose accept queue is full, any further connection attempts hang until they time out
+	async fn create_black_hole() -> (TcpListener, TcpStream) {
+		let socket = TcpSocket::new_v4().unwrap();
+		socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+		let listener = socket.listen(0).unwrap();
+		let filler = TcpStream::connect(listener.local_addr().unwrap())
+			.await
+			.unwrap();
+		(listener, filler)
+	}
+
+	// a server answering every connection with an empty 200 response, counting the connections
+	async fn create_counting_server() -> (SocketAddr, Arc<AtomicUsize>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		let connections = Arc::new(AtomicUsize::new(0));
+		let counted = connections.clone();
+		tokio::spawn(async move {
+			while let Ok((mut stream, _)) = listener.accept().await {
+				counted.fetch_add(1, Ordering::SeqCst);
+				tokio::spawn(async move {
+					let mut request = [0; 1024];
+					let _ = stream.read(&mut request).await;
+					let _ = stream
+						.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+						.await;
+				});
+			}
+		});
+		(addr, connections)
+	}
+
 	// endregion: Functions
 
 	// region: Happy Path HTTP hosts
@@ -28,17 +90,16 @@ pub mod integration {
 			when.path("/hello");
 			then.status(200);
 		});
-		let can_connect = CanIConnect {
-			http: vec![server.url("/hello"), server.url("/nonexistent")],
-			tcp: vec![server.address().to_string()],
-			timeout: 5,
-			http_client: None,
-			server_mode: false,
-			listen_addr: String::from(""),
-		};
+		let can_connect = CanIConnect::builder()
+			.http_hosts([server.url("/hello"), server.url("/nonexistent")])
+			.tcp_hosts([server.address().to_string()])
+			.timeout(Duration::from_secs(5))
+			.concurrency(10)
+			.build()
+			.unwrap();
 		for url in &can_connect.http {
-			match can_connect.can_connect(ConnectionType::HTTP, url).await {
-				Ok(success) => assert!(success),
+			match can_connect.can_connect("http", url).await {
+				Ok(info) => assert!(info.remote_addr.is_some()),
 				Err(e) => {
 					panic!("expected success but got error: {}", &e);
 				}
@@ -52,31 +113,27 @@ pub mod integration {
 	#[tokio::test]
 	async fn connection_timeout_can_connect_test() {
 		let server = create_server();
-		let timeout: usize = 5;
+		let timeout = Duration::from_secs(5);
 		server.mock(|when, then| {
 			when.path("/timeout");
-			then.status(200).delay(Duration::from_secs(timeout as u64));
+			then.status(200).delay(timeout);
 		});
-		let can_connect = CanIConnect {
-			http: vec![server.url("/timeout")],
-			tcp: vec![server.address().to_string()],
-			timeout,
-			server_mode: false,
-			listen_addr: String::from(""),
-			http_client: Some(
+		let can_connect = CanIConnect::builder()
+			.http_hosts([server.url("/timeout")])
+			.tcp_hosts([server.address().to_string()])
+			.timeout(timeout)
+			.concurrency(10)
+			.http_client(
 				Client::builder()
 					.timeout(Duration::from_secs(1))
 					.build()
 					.unwrap(),
-			),
-		};
+			)
+			.build()
+			.unwrap();
 		for url in &can_connect.http {
-			match can_connect.can_connect(ConnectionType::HTTP, url).await {
-				Ok(success) => assert!(
-					!success,
-					"expecting time out error but got success: {}",
-					success
-				),
+			match can_connect.can_connect("http", url).await {
+				Ok(info) => panic!("expecting time out error but got success: {:?}", info),
 				Err(e) => {
 					println!("{e:?}");
 					match &e {
@@ -93,6 +150,1615 @@ pub mod integration {
 	}
 	// endregion: Unhappy Path HTTP hosts: Connection timeout
 
+	// region: Sub-second and per phase timeouts
+	#[tokio::test]
+	async fn sub_second_timeouts_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/slow");
+			then.status(200).delay(Duration::from_secs(2));
+		});
+		let (black_hole, _filler) = create_black_hole().await;
+
+		// the total timeout bounds the HTTP request
+		let start = Instant::now();
+		let result = CanIConnect::builder()
+			.http_host(server.url("/slow"))
+			.timeout(Duration::from_millis(250))
+			.build()
+			.unwrap()
+			.probe(&Target::http(&server.url("/slow")))
+			.await;
+		assert!(start.elapsed() < Duration::from_secs(1));
+		assert_eq!(result.error_kind, Some(ErrorKind::Timeout));
+		assert_eq!(
+			result.error.as_deref(),
+			Some("request took longer than 250ms")
+		);
+
+		// the connect timeout fires long before the total timeout
+		let target = black_hole.local_addr().unwrap().to_string();
+		let start = Instant::now();
+		let result = CanIConnect::builder()
+			.tcp_host(&target)
+			.timeout(Duration::from_secs(5))
+			.connect_timeout(Duration::from_millis(200))
+			.build()
+			.unwrap()
+			.probe(&Target::tcp(&target))
+			.await;
+		assert!(start.elapsed() < Duration::from_secs(1));
+		assert_eq!(result.error_kind, Some(ErrorKind::Timeout));
+		assert_eq!(
+			result.error.as_deref(),
+			Some("connection was not established within 200ms")
+		);
+	}
+
+	#[test]
+	fn payload_timeouts_test() {
+		let payload: CanIConnectPayload = serde_json::from_value(json!({
+			"tcp_hosts": ["example.com:443"],
+			"timeout": "1.5s",
+			"connect_timeout": "250ms",
+			"tls_timeout": 1
+		}))
+		.unwrap();
+		assert_eq!(payload.timeout, Duration::from_millis(1500));
+		assert_eq!(payload.connect_timeout, Some(Duration::from_millis(250)));
+		assert_eq!(payload.tls_timeout, Some(Duration::from_secs(1)));
+
+		// bare numbers, as numbers or strings, are still seconds
+		for timeout in [json!(5), json!("5"), Value::Null] {
+			let payload: CanIConnectPayload =
+				serde_json::from_value(json!({ "tcp_hosts": ["example.com:443"], "timeout": timeout }))
+					.unwrap();
+			assert_eq!(payload.timeout, Duration::from_secs(5));
+			assert_eq!(payload.connect_timeout, None);
+		}
+		assert!(serde_json::from_value::<CanIConnectPayload>(json!({ "timeout": "soon" })).is_err());
+	}
+
+	#[test]
+	fn payload_limits_test() {
+		let build = |payload: Value| {
+			let payload: CanIConnectPayload = serde_json::from_value(payload).unwrap();
+			build_can_i_connect(&payload, &ServerLimits::default())
+		};
+		for (payload, error) in [
+			(
+				json!({ "tcp_hosts": ["db:5432"], "timeout": "1h" }),
+				"'timeout' must be at most 5m but got 1h",
+			),
+			(
+				json!({ "targets": ["tcp://db:5432?timeout=1h"] }),
+				"'timeout' must be at most 5m but got 1h",
+			),
+			(
+				json!({ "tcp_hosts": ["db:5432"], "retries": 1000 }),
+				"'retries' must be at most 10 but got 1000",
+			),
+			(
+				json!({ "targets": ["tcp://db:5432?retries=1000"] }),
+				"'retries' must be at most 10 but got 1000",
+			),
+			(
+				json!({ "tcp_hosts": ["db:5432"], "retry_max_delay": "1h" }),
+				"'retry_max_delay' must be at most 1m but got 1h",
+			),
+			(
+				json!({ "targets": ["tcp://db:5432?sample_interval=1h"] }),
+				"'sample_interval' must be at most 1m but got 1h",
+			),
+			(
+				json!({ "tcp_hosts": ["db:5432"], "deadline": "1000000000y" }),
+				"'deadline' must be at most 10m but got 1000000000years",
+			),
+		] {
+			let (status, Json(body)) = build(payload).unwrap_err();
+			assert_eq!(status, StatusCode::BAD_REQUEST);
+			assert_eq!(body["error"], error);
+		}
+		let can_i_connect = build(json!({
+			"targets": ["tcp://db:5432?timeout=5m&retries=10"],
+			"retry_max_delay": "1m",
+			"sample_interval": "1m",
+			"deadline": "10m",
+		}))
+		.unwrap();
+		assert_eq!(can_i_connect.deadline, Some(Duration::from_secs(600)));
+	}
+	// endregion: Sub-second and per phase timeouts
+
+	// region: Wait for hosts
+	#[tokio::test]
+	async fn wait_for_test() {
+		// a port that comes up while we are waiting
+		let addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap();
+		let can_i_connect = CanIConnect::builder()
+			.tcp_host(addr.to_string())
+			.retry(RetryPolicy {
+				retries: 0,
+				backoff: Duration::from_millis(100),
+				max_delay: Duration::from_millis(200),
+			})
+			.build()
+			.unwrap();
+		let listener = tokio::spawn(async move {
+			tokio::time::sleep(Duration::from_millis(500)).await;
+			let listener = TcpListener::bind(addr).await.unwrap();
+			tokio::time::sleep(Duration::from_secs(5)).await;
+			drop(listener);
+		});
+		let waited = wait_for(&can_i_connect, &SuccessPolicy::All, Duration::from_secs(5)).await;
+		assert_eq!(waited.outcome, Outcome::Success);
+		assert!(waited.rounds > 1, "expected several rounds: {:?}", waited);
+		assert!(waited.waited < Duration::from_secs(5));
+		listener.abort();
+
+		// give up once max wait has passed
+		let start = Instant::now();
+		let waited = wait_for(
+			&can_i_connect,
+			&SuccessPolicy::All,
+			Duration::from_millis(500),
+		)
+		.await;
+		assert_eq!(waited.outcome, Outcome::PolicyFailed);
+		// the last round may be cut off by max wait, which cancels the host instead of failing it
+		let mut unreachable = waited.report.failed_hosts();
+		unreachable.extend(waited.report.cancelled_hosts());
+		assert_eq!(unreachable, vec![addr.to_string()]);
+		assert!(start.elapsed() < Duration::from_secs(2));
+
+		// a max wait too long to represent is no limit
+		let listener = TcpListener::bind(addr).await.unwrap();
+		let waited = wait_for(&can_i_connect, &SuccessPolicy::All, Duration::MAX).await;
+		assert_eq!(waited.outcome, Outcome::Success);
+		drop(listener);
+	}
+	// endregion: Wait for hosts
+
+	// region: Source address
+	#[tokio::test]
+	async fn source_address_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/hello");
+			then.status(200);
+		});
+		let echo = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		let echo_addr = echo.local_addr().unwrap();
+		tokio::spawn(async move {
+			let mut buf = [0; 512];
+			while let Ok((len, from)) = echo.recv_from(&mut buf).await {
+				let _ = echo.send_to(&buf[..len], from).await;
+			}
+		});
+		let hosts = CanIConnect::builder()
+			.http_host(server.url("/hello"))
+			.tcp_host(server.address().to_string())
+			.target(format!("udp://{}", echo_addr).parse::<Target>().unwrap());
+
+		// the whole of 127.0.0.0/8 lives on the loopback interface
+		let source = "127.0.0.2".parse::<IpAddr>().unwrap();
+		let report = hosts
+			.clone()
+			.source_address(source)
+			.build()
+			.unwrap()
+			.connection_report()
+			.await;
+		for result in &report.results {
+			assert!(result.success, "expected success but got: {}", result);
+			assert_eq!(result.local_ip, Some(source));
+			assert!(result.to_string().contains("from 127.0.0.2"));
+		}
+		// without a source address the routing table picks one
+		for result in hosts
+			.clone()
+			.build()
+			.unwrap()
+			.connection_report()
+			.await
+			.results
+		{
+			assert!(result.success, "expected success but got: {}", result);
+			assert!(result.local_ip.is_some_and(|ip| ip.is_loopback()));
+		}
+
+		// an IPv6 source address can't reach an IPv4 host
+		let report = hosts
+			.clone()
+			.source_address("::1".parse().unwrap())
+			.build()
+			.unwrap()
+			.connection_report()
+			.await;
+		for result in &report.results {
+			assert!(!result.success, "expected failure but got: {}", result);
+			assert_eq!(result.error_kind, Some(ErrorKind::Connection));
+		}
+		// an address that is not on this host can't be bound to
+		let unbound = hosts
+			.clone()
+			.source_address("192.0.2.1".parse().unwrap())
+			.build()
+			.unwrap()
+			.connection_report()
+			.await;
+		for result in &unbound.results {
+			assert!(!result.success, "expected failure but got: {}", result);
+		}
+		assert_eq!(unbound.results[1].error_kind, Some(ErrorKind::Connection));
+
+		let missing = hosts
+			.interface("does-not-exist0")
+			.build()
+			.unwrap()
+			.connection_report()
+			.await;
+		for result in &missing.results {
+			assert!(!result.success, "expected failure but got: {}", result);
+		}
+	}
+
+	#[test]
+	fn payload_source_test() {
+		let payload: CanIConnectPayload = serde_json::from_value(json!({
+			"tcp_hosts": ["db:5432"],
+			"source_address": "10.0.0.5",
+			"interface": "eth1",
+		}))
+		.unwrap();
+		assert_eq!(payload.source_address, Some("10.0.0.5".parse().unwrap()));
+		assert_eq!(payload.interface.as_deref(), Some("eth1"));
+		assert!(serde_json::from_value::<CanIConnectPayload>(json!({
+			"tcp_hosts": ["db:5432"],
+			"source_address": "not an address",
+		}))
+		.is_err());
+	}
+	// endregion: Source address
+
+	// region: CIDR blocks and port ranges
+	#[tokio::test]
+	async fn expansion_test() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let port = listener.local_addr().unwrap().port();
+		// 127.0.0.1 is listening, 127.0.0.2 is not
+		let cidr = format!("127.0.0.0/30:{}", port);
+		let range = format!("db=localhost:{}-{}", port, port.saturating_add(1));
+		let can_i_connect = CanIConnect::builder()
+			.tcp_hosts([cidr.clone(), range])
+			.build()
+			.unwrap();
+		assert_eq!(can_i_connect.hosts_total(), 4);
+		let report = can_i_connect.connection_report().await;
+		let targets = report
+			.results
+			.iter()
+			.map(|result| result.target.clone())
+			.collect::<Vec<String>>();
+		assert_eq!(
+			targets,
+			vec![
+				format!("127.0.0.1:{}", port),
+				format!("127.0.0.2:{}", port),
+				format!("localhost:{}", port),
+				format!("localhost:{}", port + 1),
+			]
+		);
+		assert!(report.results[2..]
+			.iter()
+			.all(|result| result.tag.as_deref() == Some("db")));
+
+		let expansions = report.expansions();
+		assert_eq!(expansions.len(), 2);
+		assert_eq!(expansions[0].spec, cidr);
+		assert_eq!(expansions[0].hosts_total, 2);
+		assert_eq!(expansions[0].hosts_successful, 1);
+		assert_eq!(
+			expansions[0].failed_hosts_list,
+			vec![format!("127.0.0.2:{}", port)]
+		);
+		assert_eq!(
+			expansions[1].spec,
+			format!("localhost:{}-{}", port, port + 1)
+		);
+		assert_eq!(expansions[1].hosts_successful, 1);
+
+		// the cap is checked before anything is probed
+		assert!(matches!(
+			CanIConnect::builder().tcp_host("10.0.0.0/16:5432").build(),
+			Err(Error::InvalidTargets(problems))
+				if matches!(problems[..], [Error::ExpansionTooLarge(_, 256)])
+		));
+		assert!(CanIConnect::builder()
+			.tcp_host("10.0.0.0/22:5432")
+			.max_expansion(1024)
+			.build()
+			.is_ok());
+	}
+
+	#[tokio::test]
+	async fn can_i_connect_expansion_test() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(Uri::from_static("/can-i-connect")),
+			HeaderMap::new(),
+			Json(json!({
+				"tcp_hosts": [format!("127.0.0.0/30:{}", port)],
+				"success_policy": "any",
+			})),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::OK);
+		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+		let body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+		assert_eq!(
+			body_json["connection_report"]["expanded"],
+			json!([{
+				"spec": format!("127.0.0.0/30:{}", port),
+				"hosts_total": 2,
+				"hosts_successful": 1,
+				"failed_hosts_list": [format!("127.0.0.2:{}", port)],
+			}])
+		);
+		assert_eq!(
+			body_json["connection_report"]["results"][1]["expanded_from"],
+			json!(format!("127.0.0.0/30:{}", port))
+		);
+
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(Uri::from_static("/can-i-connect")),
+			HeaderMap::new(),
+			Json(json!({
+				"tcp_hosts": ["10.0.0.0/24:5432"],
+				"max_expansion": 100,
+			})),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+		// clients can not raise the server's cap
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(Uri::from_static("/can-i-connect")),
+			HeaderMap::new(),
+			Json(json!({
+				"tcp_hosts": ["10.0.0.0/8:1-65535"],
+				"max_expansion": 1_000_000_000_000u64,
+			})),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+		let body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+		assert_eq!(
+			body_json["error"],
+			json!("'max_expansion' must be at most 256 but got 1000000000000")
+		);
+
+		// and requests that don't set it get the server's cap
+		let response = can_i_connect_handler(
+			State(ServerLimits {
+				max_expansion: 4,
+				..ServerLimits::default()
+			}),
+			OriginalUri(Uri::from_static("/can-i-connect")),
+			HeaderMap::new(),
+			Json(json!({ "tcp_hosts": ["10.0.0.0/29:5432"] })),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+	// endregion: CIDR blocks and port ranges
+
+	// region: Targets
+	#[tokio::test]
+	async fn targets_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/health");
+			then.status(204);
+		});
+		let (black_hole, _filler) = create_black_hole().await;
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap();
+		// answers every datagram
+		let echo = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		let echo_addr = echo.local_addr().unwrap();
+		tokio::spawn(async move {
+			let mut buf = [0; 512];
+			while let Ok((len, from)) = echo.recv_from(&mut buf).await {
+				let _ = echo.send_to(&buf[..len], from).await;
+			}
+		});
+		let closed_udp = tokio::net::UdpSocket::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap();
+		// accepts connections but never says a word
+		let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let silent_addr = silent.local_addr().unwrap();
+		tokio::spawn(async move {
+			let mut connections = vec![];
+			while let Ok((stream, _)) = silent.accept().await {
+				connections.push(stream);
+			}
+		});
+
+		let targets = [
+			format!("tcp://{}", server.address()),
+			format!("{}?expect=2xx", server.url("/health")),
+			format!("web={}?expect=200", server.url("/health")),
+			format!("udp://{}", echo_addr),
+			format!("!udp://{}", closed_udp),
+			format!(
+				"tcp://{}?connect_timeout=200ms",
+				black_hole.local_addr().unwrap()
+			),
+			format!("tls://localhost:{}?tls_timeout=300ms", silent_addr.port()),
+			format!("tls://{}", server.address()),
+			format!("!tcp://{}", closed_addr),
+		];
+		let start = Instant::now();
+		let report = CanIConnect::builder()
+			.targets(
+				targets
+					.iter()
+					.map(|target| target.parse::<Target>().unwrap()),
+			)
+			.timeout(Duration::from_secs(5))
+			.build()
+			.unwrap()
+			.connection_report()
+			.await;
+		// the per target timeouts kick in long before the 5s timeout
+		assert!(start.elapsed() < Duration::from_secs(2));
+		let results = report.results;
+		assert_eq!(results.len(), targets.len());
+		for (result, target) in results.iter().zip(&targets) {
+			println!("{}", result);
+			assert_eq!(
+				&result.target,
+				target.trim_start_matches('!').trim_start_matches("web=")
+			);
+		}
+
+		assert!(results[0].success);
+		assert_eq!(results[0].protocol, "tcp");
+		assert!(results[1].success);
+		assert_eq!(results[1].http_status, Some(204));
+		assert!(!results[2].success);
+		assert_eq!(results[2].tag.as_deref(), Some("web"));
+		assert_eq!(results[2].error_kind, Some(ErrorKind::Http));
+		assert!(results[2]
+			.error
+			.as_deref()
+			.unwrap()
+			.ends_with("responded with status 204, expected 200"));
+		assert!(results[3].success, "{}", results[3]);
+		assert_eq!(results[3].protocol, "udp");
+		assert_eq!(results[3].port_state, Some(PortState::Open));
+		assert_eq!(results[4].status, ProbeStatus::Blocked);
+		assert_eq!(results[4].blocked, Some(BlockKind::Rejected));
+		assert_eq!(
+			results[5].error.as_deref(),
+			Some("connection was not established within 200ms")
+		);
+		assert_eq!(
+			results[6].error.as_deref(),
+			Some("TLS handshake did not complete within 300ms")
+		);
+		// the mock server does not speak TLS
+		assert_eq!(results[7].protocol, "tls");
+		assert_eq!(results[7].error_kind, Some(ErrorKind::Tls));
+		assert_eq!(results[8].status, ProbeStatus::Blocked);
+	}
+
+	#[test]
+	fn payload_targets_test() {
+		let payload: CanIConnectPayload = serde_json::from_value(json!({
+			"targets": ["tcp://db:5432?timeout=2s", "!egress=tls://example.com:443"]
+		}))
+		.unwrap();
+		let targets = build_can_i_connect(&payload, &ServerLimits::default())
+			.unwrap()
+			.targets;
+		assert_eq!(targets[0].address, "db:5432");
+		assert_eq!(targets[0].overrides.timeout, Some(Duration::from_secs(2)));
+		assert!(targets[1].must_fail);
+		assert_eq!(
+			serde_json::to_value(&targets).unwrap(),
+			json!(["tcp://db:5432?timeout=2s", "!egress=tls://example.com:443"])
+		);
+	}
+
+	#[tokio::test]
+	async fn invalid_hosts_are_reported_together_test() {
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(Uri::from_static("/can-i-connect")),
+			HeaderMap::new(),
+			Json(json!({
+				"http_hosts": ["example.com"],
+				"tcp_hosts": ["db.internal", "::1:5432", "cache:6379", "cache:6379"],
+				"targets": ["db:5432", "ldap://ldap.internal:389"],
+			})),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+		let body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+		assert_eq!(
+			body_json["problems"],
+			json!([
+				"db:5432 is not a valid target: expected <scheme>://<host> e.g. tcp://example.com:443",
+				"example.com is not a valid target: expected a URL e.g. https://example.com/health",
+				"db.internal is not a valid target: missing port, expected <host>:<port>",
+				"::1:5432 is not a valid target: IPv6 addresses must be in brackets e.g. [::1]:443",
+				"cache:6379 is not a valid target: duplicate target",
+				"ldap://ldap.internal:389 is not a valid target: no checker is registered for ldap:// targets",
+			])
+		);
+		assert!(body_json["error"]
+			.as_str()
+			.unwrap()
+			.starts_with("found 6 problem(s) with the hosts:"));
+
+		// the builder validates the same way
+		assert!(matches!(
+			CanIConnect::builder()
+				.tcp_hosts(["db.internal", "cache:6379"])
+				.http_host("example.com")
+				.build(),
+			Err(Error::InvalidTargets(problems)) if problems.len() == 2
+		));
+	}
+
+	#[tokio::test]
+	async fn silent_udp_port_test() {
+		// receive datagrams but never reply, like a DNS server sent an empty query
+		let silent = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		let also_silent = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+		let report = CanIConnect::builder()
+			.target(
+				format!("udp://{}", silent.local_addr().unwrap())
+					.parse::<Target>()
+					.unwrap(),
+			)
+			.target(
+				format!("!udp://{}", also_silent.local_addr().unwrap())
+					.parse::<Target>()
+					.unwrap(),
+			)
+			.timeout(Duration::from_millis(300))
+			.build()
+			.unwrap()
+			.connection_report()
+			.await;
+		let results = &report.results;
+		assert!(results[0].success, "{}", results[0]);
+		assert_eq!(results[0].port_state, Some(PortState::OpenFiltered));
+		assert_eq!(
+			serde_json::to_value(results[0].port_state).unwrap(),
+			json!("open|filtered")
+		);
+		// a firewall that drops the datagram looks the same, so a must-fail host passes
+		assert_eq!(results[1].status, ProbeStatus::Blocked);
+		assert_eq!(results[1].blocked, Some(BlockKind::Dropped));
+		drop((silent, also_silent));
+	}
+	// endregion: Targets
+
+	// region: Concurrent connection report
+	#[tokio::test]
+	async fn concurrent_connection_report_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path_contains("/slow");
+			then.status(200).delay(Duration::from_secs(1));
+		});
+		let http = (0..6)
+			.map(|i| server.url(format!("/slow/{}", i)))
+			.collect::<Vec<String>>();
+		let can_connect = CanIConnect::builder()
+			.http_hosts(http.clone())
+			.tcp_hosts([server.address().to_string()])
+			.timeout(Duration::from_secs(5))
+			.concurrency(7)
+			.build()
+			.unwrap();
+		let started = Instant::now();
+		let report = can_connect.connection_report().await;
+		assert!(
+			started.elapsed() < Duration::from_secs(3),
+			"expected hosts to be checked concurrently but took {:?}",
+			started.elapsed()
+		);
+		// results keep the order the hosts were supplied in
+		let mut expected = http;
+		expected.push(server.address().to_string());
+		assert_eq!(report.successful_hosts(), expected);
+		assert!(report.failed_hosts().is_empty());
+	}
+
+	#[test]
+	fn payload_concurrency_limit_test() {
+		let limits = ServerLimits {
+			max_concurrency: 5,
+			..ServerLimits::default()
+		};
+		let build = |payload: Value| {
+			let payload: CanIConnectPayload = serde_json::from_value(payload).unwrap();
+			build_can_i_connect(&payload, &limits)
+		};
+		let (status, Json(body)) = build(json!({
+			"tcp_hosts": ["example.com:443"],
+			"concurrency": 100_000,
+		}))
+		.unwrap_err();
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(
+			body["error"],
+			"'concurrency' must be at most 5 but got 100000"
+		);
+		// the default is lowered to the server's own
+		let can_i_connect = build(json!({ "tcp_hosts": ["example.com:443"] })).unwrap();
+		assert_eq!(can_i_connect.concurrency, 5);
+		let can_i_connect = build(json!({
+			"tcp_hosts": ["example.com:443"],
+			"concurrency": 5,
+		}))
+		.unwrap();
+		assert_eq!(can_i_connect.concurrency, 5);
+	}
+	// endregion: Concurrent connection report
+
+	// region: Per host probe results
+	#[tokio::test]
+	async fn probe_result_details_test() {
+		let server = create_server();
+		// grab a free port and close it again so nothing is listening on it
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let can_connect = CanIConnect::builder()
+			.tcp_hosts([server.address().to_string(), closed_addr.clone()])
+			.timeout(Duration::from_secs(5))
+			.concurrency(10)
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		assert_eq!(report.results.len(), 2);
+
+		let reachable = &report.results[0];
+		assert!(reachable.success);
+		assert_eq!(reachable.protocol, "tcp");
+		assert_eq!(reachable.resolved_ip, Some(server.address().ip()));
+		assert_eq!(reachable.attempts, 1);
+		assert!(reachable.error.is_none());
+		assert!(reachable.finished_at >= reachable.started_at);
+
+		let unreachable = &report.results[1];
+		assert!(!unreachable.success);
+		assert_eq!(unreachable.target, closed_addr);
+		assert_eq!(unreachable.error_kind, Some(ErrorKind::ConnectionRefused));
+		assert!(unreachable.error.is_some());
+		assert_eq!(report.failed_hosts(), vec![closed_addr]);
+	}
+	// endregion: Per host probe results
+
+	// region: Retries
+	#[tokio::test]
+	async fn retry_until_reachable_test() {
+		// reserve a port and only start listening on it after a few attempts have failed
+		let addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap();
+		let late_listener = tokio::spawn(async move {
+			tokio::time::sleep(Duration::from_millis(300)).await;
+			let listener = TcpListener::bind(addr).await.unwrap();
+			let _ = listener.accept().await;
+		});
+		let can_connect = CanIConnect::builder()
+			.tcp_hosts([addr.to_string()])
+			.timeout(Duration::from_secs(5))
+			.concurrency(10)
+			.retry(RetryPolicy {
+				retries: 20,
+				backoff: Duration::from_millis(50),
+				max_delay: Duration::from_millis(100),
+			})
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		let result = &report.results[0];
+		assert!(result.success, "expected success but got: {}", result);
+		assert!(result.attempts > 1);
+		assert_eq!(result.attempt_errors.len() as u32, result.attempts - 1);
+		assert!(result
+			.attempt_errors
+			.iter()
+			.all(|attempt| attempt.error_kind == ErrorKind::ConnectionRefused));
+		assert!(result.error.is_none());
+		late_listener.abort();
+	}
+
+	#[tokio::test]
+	async fn retries_exhausted_test() {
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let can_connect = CanIConnect::builder()
+			.tcp_hosts([closed_addr])
+			.timeout(Duration::from_secs(5))
+			.concurrency(10)
+			.retry(RetryPolicy {
+				retries: 2,
+				backoff: Duration::from_millis(10),
+				max_delay: Duration::from_millis(20),
+			})
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		let result = &report.results[0];
+		assert!(!result.success);
+		assert_eq!(result.attempts, 3);
+		assert_eq!(
+			result
+				.attempt_errors
+				.iter()
+				.map(|attempt| attempt.attempt)
+				.collect::<Vec<u32>>(),
+			vec![1, 2, 3]
+		);
+	}
+	// endregion: Retries
+
+	// region: Latency samples
+	#[tokio::test]
+	async fn latency_samples_test() {
+		let server = create_server();
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let uri = Uri::from_static("/can-i-connect");
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(uri),
+			HeaderMap::new(),
+			Json(json!({
+				"tcp_hosts": [server.address().to_string(), closed_addr],
+				"samples": 3,
+				"sample_interval": "10ms",
+			})),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::OK);
+		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+		let body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+		let results = &body_json["connection_report"]["results"];
+
+		let reachable = &results[0]["stats"];
+		assert_eq!(results[0]["success"], json!(true));
+		assert_eq!(reachable["samples"], json!(3));
+		assert_eq!(reachable["received"], json!(3));
+		assert_eq!(reachable["loss_percent"], json!(0.0));
+		for field in [
+			"min_ms",
+			"mean_ms",
+			"p50_ms",
+			"p95_ms",
+			"max_ms",
+			"jitter_ms",
+		] {
+			assert!(reachable[field].is_number(), "{} is missing", field);
+		}
+		assert!(reachable["min_ms"].as_f64() <= reachable["max_ms"].as_f64());
+
+		let unreachable = &results[1]["stats"];
+		assert_eq!(results[1]["success"], json!(false));
+		assert_eq!(unreachable["received"], json!(0));
+		assert_eq!(unreachable["loss_percent"], json!(100.0));
+		assert_eq!(unreachable["p50_ms"], Value::Null);
+	}
+
+	#[test]
+	fn payload_samples_limit_test() {
+		let build = |payload: Value| {
+			let payload: CanIConnectPayload = serde_json::from_value(payload).unwrap();
+			build_can_i_connect(&payload, &ServerLimits::default())
+		};
+		for payload in [
+			json!({ "tcp_hosts": ["example.com:443"], "samples": 1_000_000 }),
+			json!({ "targets": ["tcp://db:5432?samples=1000000", "tcp://cache:6379?samples=5"] }),
+		] {
+			let (status, Json(body)) = build(payload).unwrap_err();
+			assert_eq!(status, StatusCode::BAD_REQUEST);
+			assert_eq!(
+				body["error"],
+				format!("'samples' must be at most {} but got 1000000", MAX_SAMPLES)
+			);
+		}
+		let can_i_connect = build(json!({
+			"targets": ["tcp://db:5432?samples=100", "tcp://cache:6379?samples=5"],
+			"samples": MAX_SAMPLES,
+		}))
+		.unwrap();
+		assert_eq!(can_i_connect.sampling.samples, MAX_SAMPLES);
+		assert_eq!(can_i_connect.targets[1].overrides.samples, Some(5));
+	}
+	// endregion: Latency samples
+
+	// region: Deadline and cancellation
+	#[tokio::test]
+	async fn deadline_cancels_unfinished_hosts_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/slow");
+			then.status(200).delay(Duration::from_secs(3));
+		});
+		let can_connect = CanIConnect::builder()
+			.http_hosts([server.url("/slow")])
+			.tcp_hosts([server.address().to_string()])
+			.timeout(Duration::from_secs(5))
+			.concurrency(10)
+			.deadline(Duration::from_millis(500))
+			.build()
+			.unwrap();
+		let started = Instant::now();
+		let report = can_connect.connection_report().await;
+		assert!(started.elapsed() < Duration::from_secs(2));
+		assert_eq!(report.results[0].status, ProbeStatus::Cancelled);
+		assert_eq!(report.results[0].error_kind, Some(ErrorKind::Cancelled));
+		assert_eq!(report.results[1].status, ProbeStatus::Success);
+		assert_eq!(report.cancelled_hosts(), vec![server.url("/slow")]);
+		// counted once, as cancelled
+		assert!(report.failed_hosts().is_empty());
+
+		// a deadline too far off to represent is no deadline
+		let can_connect = CanIConnect::builder()
+			.tcp_host(server.address().to_string())
+			.deadline(Duration::MAX)
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		assert_eq!(report.results[0].status, ProbeStatus::Success);
+	}
+
+	#[tokio::test]
+	async fn client_disconnect_drops_probes_test() {
+		// accepts connections but never answers, so http probes hang until they are dropped
+		let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let silent_url = format!("http://{}/", silent.local_addr().unwrap());
+
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let api_url = format!("http://{}/can-i-connect", listener.local_addr().unwrap());
+		tokio::spawn(async move {
+			axum::serve(
+				listener,
+				routes_can_i_connect::routes(ServerLimits::default()),
+			)
+			.await
+			.unwrap();
+		});
+		let client = Client::builder()
+			.timeout(Duration::from_millis(500))
+			.build()
+			.unwrap();
+		let request = tokio::spawn(
+			client
+				.post(api_url)
+				.json(&json!({ "http_hosts": [silent_url], "timeout": 30 }))
+				.send(),
+		);
+		let (mut probe_stream, _) = silent.accept().await.unwrap();
+		assert!(
+			request.await.unwrap().is_err(),
+			"expected the client to give up waiting"
+		);
+
+		// once the client is gone the probe is dropped and its socket closed
+		let mut buf = [0u8; 1024];
+		let closed = tokio::time::timeout(Duration::from_secs(5), async {
+			loop {
+				match probe_stream.read(&mut buf).await {
+					Ok(0) | Err(_) => break,
+					Ok(_) => continue,
+				}
+			}
+		})
+		.await;
+		assert!(
+			closed.is_ok(),
+			"expected probe socket to be closed after the client disconnected"
+		);
+	}
+	// endregion: Deadline and cancellation
+
+	// region: Probe events
+	#[tokio::test]
+	async fn probe_events_test() {
+		let server = create_server();
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let can_connect = CanIConnect::builder()
+			.tcp_hosts([server.address().to_string(), closed_addr])
+			.build()
+			.unwrap();
+		let (sender, mut receiver) = mpsc::unbounded_channel();
+		let report = can_connect.connection_report_with_events(sender).await;
+		let mut events = vec![];
+		while let Ok(event) = receiver.try_recv() {
+			events.push(event);
+		}
+		assert_eq!(events.len(), 5);
+		for index in 0..2 {
+			let started = events.iter().position(
+				|event| matches!(event, ProbeEvent::ProbeStarted { index: i, .. } if *i == index),
+			);
+			let finished = events.iter().position(
+				|event| matches!(event, ProbeEvent::ProbeFinished { index: i, .. } if *i == index),
+			);
+			assert!(started.unwrap() < finished.unwrap());
+		}
+		match events.last().unwrap() {
+			ProbeEvent::ReportComplete {
+				hosts_total,
+				hosts_successful,
+				hosts_failed,
+				hosts_cancelled,
+				..
+			} => {
+				assert_eq!(
+					(
+						*hosts_total,
+						*hosts_successful,
+						*hosts_failed,
+						*hosts_cancelled
+					),
+					(2, 1, 1, 0)
+				);
+			}
+			event => panic!("expected the report to complete last, got {:?}", event),
+		}
+		assert_eq!(
+			report.successful_hosts(),
+			vec![server.address().to_string()]
+		);
+	}
+
+	#[tokio::test]
+	async fn event_stream_shows_progress_test() {
+		let server = create_server();
+		let (black_hole, _filler) = create_black_hole().await;
+		let hanging = black_hole.local_addr().unwrap().to_string();
+		let can_connect = CanIConnect::builder()
+			.tcp_hosts([hanging.clone(), server.address().to_string()])
+			.timeout(Duration::from_secs(2))
+			.build()
+			.unwrap();
+		let started = Instant::now();
+		let mut events = Box::pin(can_connect.event_stream());
+		let mut finished = vec![];
+		let mut running = vec![];
+		while finished.is_empty() {
+			match events.next().await.unwrap() {
+				ProbeEvent::ProbeStarted { target, .. } => running.push(target),
+				ProbeEvent::ProbeFinished { result, .. } => finished.push(result.target),
+				event => panic!("unexpected event {:?}", event),
+			}
+		}
+		// the reachable host is reported while the other one is still hanging
+		assert!(started.elapsed() < Duration::from_secs(1));
+		assert_eq!(finished, vec![server.address().to_string()]);
+		assert!(running.contains(&hanging));
+
+		let rest = events.collect::<Vec<ProbeEvent>>().await;
+		assert!(matches!(
+			rest.last(),
+			Some(ProbeEvent::ReportComplete {
+				hosts_failed: 1,
+				..
+			})
+		));
+	}
+
+	#[tokio::test]
+	async fn streamed_route_test() {
+		let server = create_server();
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let payload = json!({ "tcp_hosts": [server.address().to_string(), closed_addr] });
+
+		let mut headers = HeaderMap::new();
+		headers.insert(
+			header::ACCEPT,
+			HeaderValue::from_static("application/x-ndjson"),
+		);
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(Uri::from_static("/can-i-connect")),
+			headers,
+			Json(payload.clone()),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.headers()[header::CONTENT_TYPE],
+			"application/x-ndjson"
+		);
+		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+		let lines = String::from_utf8(body_bytes.to_vec())
+			.unwrap()
+			.lines()
+			.map(|line| serde_json::from_str::<Value>(line).unwrap())
+			.collect::<Vec<Value>>();
+		assert_eq!(lines.len(), 5);
+		let count = |event: &str| lines.iter().filter(|line| line["event"] == event).count();
+		assert_eq!(count("probe_started"), 2);
+		assert_eq!(count("probe_finished"), 2);
+		let complete = lines.last().unwrap();
+		assert_eq!(complete["event"], json!("report_complete"));
+		assert_eq!(complete["hosts_successful"], json!(1));
+		assert_eq!(complete["success"], json!(false));
+		assert_eq!(complete["outcome"], json!("policy_failed"));
+
+		let mut headers = HeaderMap::new();
+		headers.insert(
+			header::ACCEPT,
+			HeaderValue::from_static("text/event-stream"),
+		);
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(Uri::from_static("/can-i-connect")),
+			headers,
+			Json(payload),
+		)
+		.await
+		.into_response();
+		assert_eq!(
+			response.headers()[header::CONTENT_TYPE],
+			"text/event-stream"
+		);
+		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+		let body = String::from_utf8(body_bytes.to_vec()).unwrap();
+		assert_eq!(body.matches("event: probe_started").count(), 2);
+		assert_eq!(body.matches("event: probe_finished").count(), 2);
+		assert!(body.contains("event: report_complete"));
+	}
+
+	#[tokio::test]
+	async fn tap_stream_test() {
+		let server = create_server();
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let can_connect = CanIConnect::builder()
+			.tcp_hosts([closed_addr.clone(), server.address().to_string()])
+			.build()
+			.unwrap();
+		let path = std::env::temp_dir().join(format!("can-i-connect-{}.tap", server.port()));
+		let mut output = ReportOutput::open(Some(&path)).unwrap();
+		let (report, written) = tap::stream(&can_connect, &mut output).await;
+		assert!(written.is_ok());
+		assert_eq!(report.results.len(), 2);
+
+		let tap = std::fs::read_to_string(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let lines = tap.lines().collect::<Vec<&str>>();
+		assert_eq!(&lines[..2], ["TAP version 13", "1..2"]);
+		let tests = lines
+			.iter()
+			.filter(|line| line.starts_with("ok ") || line.starts_with("not ok "))
+			.collect::<Vec<_>>();
+		assert_eq!(tests.len(), 2);
+		// numbered in the order the hosts finish
+		let reachable = format!(" - {} [tcp]", server.address());
+		assert!(tests
+			.iter()
+			.any(|line| line.starts_with("ok ") && line.ends_with(&reachable)));
+		assert!(tap.contains(&format!(
+			"- {} [tcp]\n  ---\n  error_kind: connection_refused",
+			closed_addr
+		)));
+	}
+	// endregion: Probe events
+
+	// region: Address selection
+	#[tokio::test]
+	async fn all_addresses_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/hello");
+			then.status(200);
+		});
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let can_connect = CanIConnect::builder()
+			.http_hosts([server.url("/hello")])
+			.tcp_hosts([server.address().to_string(), closed_addr])
+			.timeout(Duration::from_secs(5))
+			.concurrency(10)
+			.address_mode(AddressMode::All)
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		for result in &report.results[..2] {
+			assert!(result.success, "expected success but got: {}", result);
+			assert_eq!(result.addresses.len(), 1);
+			assert!(result.addresses[0].success);
+			assert_eq!(result.addresses[0].family, IpFamily::Ipv4);
+		}
+		let unreachable = &report.results[2];
+		assert!(!unreachable.success);
+		assert_eq!(unreachable.error_kind, Some(ErrorKind::ConnectionRefused));
+		assert_eq!(unreachable.addresses.len(), 1);
+		assert_eq!(
+			unreachable.addresses[0].error_kind,
+			Some(ErrorKind::ConnectionRefused)
+		);
+	}
+
+	#[tokio::test]
+	async fn happy_eyeballs_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/hello");
+			then.status(200);
+		});
+		let can_connect = CanIConnect::builder()
+			.http_hosts([server.url("/hello")])
+			.tcp_hosts([server.address().to_string()])
+			.timeout(Duration::from_secs(5))
+			.concurrency(10)
+			.address_mode(AddressMode::HappyEyeballs)
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		for result in &report.results {
+			assert!(result.success, "expected success but got: {}", result);
+			assert_eq!(result.family, Some(IpFamily::Ipv4));
+		}
+
+		// a hanging address does not hold up the race, the next attempt starts after the attempt delay
+		let (black_hole, _filler) = create_black_hole().await;
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap();
+		let started = Instant::now();
+		let winner = happy_eyeballs(
+			vec![
+				black_hole.local_addr().unwrap(),
+				closed_addr,
+				server.address().to_owned(),
+			],
+			Duration::from_secs(5),
+			&Source::default(),
+		)
+		.await
+		.unwrap();
+		assert_eq!(winner.remote_addr, Some(server.address().to_owned()));
+		assert!(started.elapsed() < Duration::from_secs(2));
+
+		let all_failed = happy_eyeballs(
+			vec![closed_addr],
+			Duration::from_secs(5),
+			&Source::default(),
+		)
+		.await;
+		assert_eq!(
+			all_failed.map_err(|e| e.kind()).unwrap_err(),
+			ErrorKind::ConnectionRefused
+		);
+	}
+
+	#[tokio::test]
+	async fn happy_eyeballs_connects_once_test() {
+		// the winning connection is used for the request or handshake, not opened again
+		for scheme in ["http", "tls"] {
+			let (addr, connections) = create_counting_server().await;
+			let can_connect = CanIConnect::builder()
+				.target(format!("{}://{}", scheme, addr).parse().unwrap())
+				.address_mode(AddressMode::HappyEyeballs)
+				.build()
+				.unwrap();
+			let result = &can_connect.connection_report().await.results[0];
+			// the server does not speak TLS
+			assert_eq!(result.success, scheme == "http", "{}", result);
+			assert_eq!(connections.load(Ordering::SeqCst), 1, "{}", result);
+		}
+	}
+
+	#[tokio::test]
+	async fn address_family_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/hello");
+			then.status(200);
+		});
+		let report = |family| {
+			let can_connect = CanIConnect::builder()
+				.http_hosts([server.url("/hello")])
+				.tcp_hosts([server.address().to_string()])
+				.timeout(Duration::from_secs(5))
+				.concurrency(10)
+				.family(family)
+				.build()
+				.unwrap();
+			async move { can_connect.connection_report().await }
+		};
+		// the mock server only listens on IPv4
+		for family in [
+			AddressFamily::PreferIpv4,
+			AddressFamily::Ipv4Only,
+			AddressFamily::PreferIpv6,
+		] {
+			for result in report(family).await.results {
+				assert!(result.success, "expected success but got: {}", result);
+				assert_eq!(result.family, Some(IpFamily::Ipv4));
+			}
+		}
+		for result in report(AddressFamily::Ipv6Only).await.results {
+			assert!(!result.success);
+			assert_eq!(result.error_kind, Some(ErrorKind::NoAddressForFamily));
+		}
+	}
+
+	#[tokio::test]
+	async fn custom_resolver_test() {
+		struct StaticResolver(SocketAddr);
+
+		#[async_trait]
+		impl DnsResolver for StaticResolver {
+			async fn resolve(&self, host: &str) -> Result<Vec<SocketAddr>, Error> {
+				match host.starts_with("db.internal:") {
+					true => Ok(vec![self.0]),
+					false => Err(Error::DnsNxDomain(host.to_string())),
+				}
+			}
+		}
+
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/hello");
+			then.status(200);
+		});
+		let can_connect = CanIConnect::builder()
+			.http_host(format!("http://db.internal:{}/hello", server.port()))
+			.tcp_hosts([
+				format!("db.internal:{}", server.port()),
+				String::from("elsewhere.internal:80"),
+			])
+			.resolver(StaticResolver(server.address().to_owned()))
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		assert!(report.results[0].success, "{}", report.results[0]);
+		assert!(report.results[1].success, "{}", report.results[1]);
+		assert_eq!(report.results[2].error_kind, Some(ErrorKind::DnsNxdomain));
+	}
+
+	#[tokio::test]
+	async fn http_prefers_ipv4_test() {
+		struct Ipv6FirstResolver(u16);
+
+		#[async_trait]
+		impl DnsResolver for Ipv6FirstResolver {
+			async fn resolve(&self, _host: &str) -> Result<Vec<SocketAddr>, Error> {
+				Ok(vec![
+					SocketAddr::from((Ipv6Addr::LOCALHOST, self.0)),
+					SocketAddr::from((Ipv4Addr::LOCALHOST, self.0)),
+				])
+			}
+		}
+
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/hello");
+			then.status(200);
+		});
+		// the mock server only listens on IPv4, which is tried first whatever the resolver's order
+		let can_connect = CanIConnect::builder()
+			.http_host(format!("http://db.internal:{}/hello", server.port()))
+			.resolver(Ipv6FirstResolver(server.port()))
+			.build()
+			.unwrap();
+		let result = &can_connect.connection_report().await.results[0];
+		assert!(result.success, "{}", result);
+		assert_eq!(result.family, Some(IpFamily::Ipv4));
+	}
+	// endregion: Address selection
+
+	// region: Pluggable checkers
+	#[tokio::test]
+	async fn custom_checker_test() {
+		// "connects" to `fake://up` only, without touching the network
+		struct FakeChecker;
+
+		#[async_trait]
+		impl Checker for FakeChecker {
+			fn protocol(&self) -> &'static str {
+				"fake"
+			}
+			fn authority(&self, target: &str) -> Result<String, Error> {
+				Ok(format!("{}:80", target.trim_start_matches("fake://")))
+			}
+			async fn connect(
+				&self,
+				_can_i_connect: &CanIConnect,
+				target: &str,
+				_addr: SocketAddr,
+			) -> Result<ConnectionInfo, Error> {
+				Err(Error::ConnectionRefused(target.to_string()))
+			}
+			async fn check(
+				&self,
+				_can_i_connect: &CanIConnect,
+				target: &str,
+			) -> Result<ConnectionInfo, Error> {
+				match self.authority(target)?.as_str() {
+					"up:80" => Ok(ConnectionInfo::default()),
+					_ => Err(Error::ConnectionRefused(target.to_string())),
+				}
+			}
+		}
+
+		let can_connect = CanIConnect::builder()
+			.http_hosts(["fake://up", "!fake://down"])
+			.checker("fake", FakeChecker)
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		assert!(report.results[0].success, "{}", report.results[0]);
+		assert_eq!(report.results[0].protocol, "fake");
+		assert_eq!(report.results[1].status, ProbeStatus::Blocked);
+
+		// the builder refuses schemes without a checker, a report skipping the builder fails them
+		assert!(matches!(
+			CanIConnect::builder()
+				.http_host("ftp://example.com/")
+				.build(),
+			Err(Error::InvalidTargets(_))
+		));
+		let can_connect = CanIConnect {
+			http: vec![String::from("ftp://example.com/")],
+			..Default::default()
+		};
+		let report = can_connect.connection_report().await;
+		assert!(!report.results[0].success);
+		assert_eq!(report.results[0].protocol, "ftp");
+		assert_eq!(
+			report.results[0].error.as_deref(),
+			Some("no checker is registered for ftp:// targets")
+		);
+	}
+	// endregion: Pluggable checkers
+
+	// region: Failure classification
+	#[tokio::test]
+	async fn tls_failure_is_classified_test() {
+		let server = create_server();
+		server.mock(|when, then| {
+			when.path("/plain");
+			then.status(200);
+		});
+		// speak TLS to a plain text HTTP server
+		let url = format!("https://{}/plain", server.address());
+		match handle_http(&url, None, &Timeouts::default(), &Source::default()).await {
+			Ok(info) => panic!("expected TLS failure but got success: {:?}", info),
+			Err(e) => assert_eq!(e.kind(), ErrorKind::Tls, "unexpected error: {e:?}"),
+		}
+	}
+
+	#[tokio::test]
+	async fn refused_failure_is_classified_test() {
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap();
+		let url = format!("http://{}/", closed_addr);
+		match handle_http(&url, None, &Timeouts::default(), &Source::default()).await {
+			Ok(info) => panic!("expected connection refused but got success: {:?}", info),
+			Err(e) => assert_eq!(
+				e.kind(),
+				ErrorKind::ConnectionRefused,
+				"unexpected error: {e:?}"
+			),
+		}
+	}
+	// endregion: Failure classification
+
+	// region: Must-fail hosts
+	#[tokio::test]
+	async fn must_fail_hosts_test() {
+		let server = create_server();
+		let egress = create_server();
+		let (black_hole, _filler) = create_black_hole().await;
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let can_connect = CanIConnect::builder()
+			.tcp_hosts([
+				format!("!{}", closed_addr),
+				format!("!{}", black_hole.local_addr().unwrap()),
+				format!("!egress={}", egress.address()),
+				server.address().to_string(),
+			])
+			.timeout(Duration::from_secs(1))
+			.concurrency(10)
+			.build()
+			.unwrap();
+		let report = can_connect.connection_report().await;
+		let results = &report.results;
+		assert!(results[0].must_fail && results[0].success);
+		assert_eq!(results[0].target, closed_addr);
+		assert_eq!(results[0].status, ProbeStatus::Blocked);
+		assert_eq!(results[0].blocked, Some(BlockKind::Rejected));
+		assert_eq!(results[1].status, ProbeStatus::Blocked);
+		assert_eq!(results[1].blocked, Some(BlockKind::Dropped));
+		assert!(!results[2].success);
+		assert_eq!(results[2].tag.as_deref(), Some("egress"));
+		assert_eq!(results[2].status, ProbeStatus::UnexpectedlyReachable);
+		assert!(!results[3].must_fail && results[3].success);
+
+		assert_eq!(
+			report.successful_hosts(),
+			vec![server.address().to_string()]
+		);
+		assert!(report.failed_hosts().is_empty());
+		assert_eq!(report.blocked_hosts().len(), 2);
+		assert_eq!(
+			report.unexpectedly_reachable_hosts(),
+			vec![egress.address().to_string()]
+		);
+	}
+
+	#[tokio::test]
+	async fn can_i_connect_must_fail_hosts_test() {
+		let server = create_server();
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let uri = Uri::from_static("/can-i-connect");
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(uri),
+			HeaderMap::new(),
+			Json(json!({
+				"must_fail_tcp_hosts": [closed_addr, server.address().to_string()],
+			})),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::OK);
+		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+		let body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+		let report = &body_json["connection_report"];
+		assert_eq!(body_json["success"], json!(false));
+		assert_eq!(
+			report["correctly_blocked"],
+			json!({"hosts_blocked": 1, "blocked_hosts_list": [closed_addr]})
+		);
+		assert_eq!(
+			report["unexpectedly_reachable"],
+			json!({
+				"hosts_unexpectedly_reachable": 1,
+				"unexpectedly_reachable_hosts_list": [server.address().to_string()]
+			})
+		);
+		assert_eq!(report["results"][0]["status"], json!("blocked"));
+		assert_eq!(report["results"][0]["blocked"], json!("rejected"));
+		assert_eq!(
+			report["results"][1]["status"],
+			json!("unexpectedly_reachable")
+		);
+	}
+	// endregion: Must-fail hosts
+
+	// region: Hanging TCP hosts do not block the server
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn health_responds_while_tcp_probes_hang_test() {
+		let (black_hole, _filler) = create_black_hole().await;
+		let black_hole_addr = black_hole.local_addr().unwrap().to_string();
+		let probes = (0..16)
+			.map(|_| {
+				let host = black_hole_addr.clone();
+				tokio::spawn(async move {
+					handle_tcp(
+						&DefaultResolver,
+						&host,
+						Duration::from_secs(5),
+						AddressFamily::default(),
+						&Source::default(),
+					)
+					.await
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let health_url = format!("http://{}/health", listener.local_addr().unwrap());
+		tokio::spawn(async move {
+			axum::serve(listener, routes_health::routes())
+				.await
+				.unwrap();
+		});
+		let client = Client::builder()
+			.timeout(Duration::from_secs(1))
+			.build()
+			.unwrap();
+		let response = client.get(health_url).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+
+		for probe in &probes {
+			assert!(
+				!probe.is_finished(),
+				"expected tcp probe to still be waiting on the black hole"
+			);
+		}
+		for probe in probes {
+			probe.abort();
+		}
+	}
+	// endregion: Hanging TCP hosts do not block the server
+
 	// region: can-i-connect POST with timeout arg
 	#[tokio::test]
 	async fn can_i_connect_with_timeout_test() {
@@ -112,15 +1778,33 @@ pub mod integration {
 			"timeout": 5
 		}));
 		let uri = Uri::from_static("/can-i-connect");
-		let response = can_i_connect_handler(OriginalUri(uri), payolad_with_timeout)
-			.await
-			.into_response();
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(uri),
+			HeaderMap::new(),
+			payolad_with_timeout,
+		)
+		.await
+		.into_response();
 
 		assert_eq!(response.status(), StatusCode::OK);
 
 		// Read the response body
 		let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
-		let body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+		let mut body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+		// per host results are checked separately since they contain timings
+		let results = body_json["connection_report"]
+			.as_object_mut()
+			.unwrap()
+			.remove("results")
+			.unwrap();
+		let results = results.as_array().unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0]["target"], json!(server.url("/hello")));
+		assert_eq!(results[0]["protocol"], json!("http"));
+		assert_eq!(results[1]["protocol"], json!("tcp"));
+		assert_eq!(results[1]["resolved_ip"], json!("127.0.0.1"));
+		assert_eq!(results[1]["error_kind"], Value::Null);
 		// Check the expected body content
 		let expected_body = json!({
 			"connection_report": {
@@ -134,12 +1818,86 @@ pub mod integration {
 									format!("{}", server.url("/hello")),
 									format!("{}", server.address().to_string()),
 							]
-					}
+					},
+					"cancelled": {
+							"cancelled_hosts_list": [],
+							"hosts_cancelled": 0
+					},
+					"correctly_blocked": {
+							"blocked_hosts_list": [],
+							"hosts_blocked": 0
+					},
+					"unexpectedly_reachable": {
+							"hosts_unexpectedly_reachable": 0,
+							"unexpectedly_reachable_hosts_list": []
+					},
+					"expanded": []
 			},
-			"success": true
+			"success": true,
+			"outcome": "success",
+			"success_policy": "all"
 		});
 
 		assert_eq!(body_json, expected_body);
 	}
 	// endregion: can-i-connect POST with timeout arg
+
+	// region: can-i-connect POST with success policy
+	#[tokio::test]
+	async fn can_i_connect_success_policy_test() {
+		let server = create_server();
+		let closed_addr = TcpListener::bind("127.0.0.1:0")
+			.await
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.to_string();
+		let post = |policy: &str| {
+			Json(json!({
+				"tcp_hosts": [
+					format!("db={}", server.address()),
+					format!("cache={}", closed_addr),
+				],
+				"success_policy": policy,
+			}))
+		};
+		for (policy, success, outcome) in [
+			("all", false, "policy_failed"),
+			("any", true, "partial"),
+			("50%", true, "partial"),
+			("group:db=1", true, "partial"),
+			("group:cache=1", false, "policy_failed"),
+		] {
+			let uri = Uri::from_static("/can-i-connect");
+			let response = can_i_connect_handler(
+				State(ServerLimits::default()),
+				OriginalUri(uri),
+				HeaderMap::new(),
+				post(policy),
+			)
+			.await
+			.into_response();
+			assert_eq!(response.status(), StatusCode::OK);
+			let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+			let body_json: Value = serde_json::from_slice(&body_bytes).unwrap();
+			assert_eq!(body_json["success"], json!(success), "policy: {}", policy);
+			assert_eq!(body_json["outcome"], json!(outcome), "policy: {}", policy);
+			assert_eq!(
+				body_json["connection_report"]["results"][0]["tag"],
+				json!("db")
+			);
+		}
+
+		let uri = Uri::from_static("/can-i-connect");
+		let response = can_i_connect_handler(
+			State(ServerLimits::default()),
+			OriginalUri(uri),
+			HeaderMap::new(),
+			post("most"),
+		)
+		.await
+		.into_response();
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+	// endregion: can-i-connect POST with success policy
 }
@@ -0,0 +1,54 @@
+// allows us to: `use crate::{Error, Result};`
+pub use self::error::{Error, ErrorKind, Result};
+
+// public api
+pub use self::can_i_connect::{
+	AddressMode, AddressResult, AttemptError, BlockKind, CanIConnect, CanIConnectBuilder,
+	ConnectionReport, ExpansionSummary, IpFamily, ProbeEvent, ProbeResult, ProbeStatus,
+};
+pub use self::checker::{
+	Checker, CheckerRegistry, ConnectionInfo, HttpChecker, PortState, TcpChecker, TlsChecker,
+	UdpChecker,
+};
+pub use self::dns::{AddressFamily, DefaultResolver, DnsResolver};
+pub use self::output::OutputFormat;
+pub use self::policy::{Outcome, SuccessPolicy};
+pub use self::retry::RetryPolicy;
+pub use self::source::Source;
+pub use self::stats::{LatencyStats, Sampling};
+pub use self::target::{ExpectedStatus, Target, TargetOverrides};
+pub use self::timeouts::Timeouts;
+pub use self::wait::{wait_for, WaitReport};
+
+// modules
+pub mod can_i_connect;
+pub mod checker;
+pub mod dns;
+pub mod error;
+mod helpers;
+pub mod output;
+pub mod policy;
+pub mod retry;
+pub mod source;
+pub mod stats;
+pub mod target;
+pub mod timeouts;
+pub mod version;
+pub mod wait;
+
+// command line interface, only used by the binary
+#[cfg(feature = "cli")]
+pub mod argc;
+#[cfg(feature = "cli")]
+pub mod options;
+#[cfg(feature = "cli")]
+pub use self::helpers::create_logger;
+
+// server mode
+#[cfg(feature = "server")]
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod web;
+
+#[cfg(feature = "server")]
+mod integration_tests;
@@ -1,57 +1,73 @@
-// allows us to: `use crate::{Error, Result};`
-pub use self::error::{Error, Result};
-
-// modules
-mod argc;
-mod can_i_connect;
-mod dns;
-mod error;
-mod helpers;
-mod integration_tests;
-mod metrics;
-mod options;
-mod version;
-mod web;
-
 // imports
-use crate::can_i_connect::CanIConnect;
-use crate::metrics::start_metrics_server;
-use crate::options::Options;
-use argc::argc_app;
-use helpers::create_logger;
+#[cfg(feature = "server")]
+use can_i_connect::metrics::start_metrics_server;
+use can_i_connect::options::Options;
+use can_i_connect::output::{DocumentFormat, ReportOutput, RunInfo};
+use can_i_connect::policy::{
+	Outcome, SuccessPolicy, EXIT_COMMAND_FAILED, EXIT_COMMAND_NOT_FOUND, EXIT_CONFIG_ERROR,
+};
+use can_i_connect::{
+	argc::argc_app, create_logger, wait_for, CanIConnect, ConnectionReport, Result,
+};
 use log::{error, info};
-use reqwest::Client;
-use std::time::Duration;
+use std::io;
+use std::process::{Command, ExitCode};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
 	// options setup
 	let argc = argc_app().get_matches();
 	let options = match Options::from_argc(argc) {
 		Ok(options) => options,
-		Err(e) => panic!("{}", e),
+		Err(e) => {
+			eprintln!("{}", e);
+			return ExitCode::from(EXIT_CONFIG_ERROR);
+		}
 	};
-	// logger setup
-	create_logger(options.no_color)
-		.filter_level(options.log_level)
-		.init();
+	// logger setup, logs make way for the report on stdout
+	let mut logger = create_logger(options.no_color);
+	if options.output.writes_report() && options.output_file.is_none() {
+		logger.target(env_logger::Target::Stderr);
+	}
+	logger.filter_level(options.log_level).init();
 
 	// can_i setup
-	let can_i_connect = CanIConnect {
-		http: options.http_hosts,
-		tcp: options.tcp_hosts,
-		timeout: options.timeout,
-		server_mode: !options.listen.is_empty(),
-		listen_addr: options.listen,
-		http_client: Some(
-			Client::builder()
-				.timeout(Duration::from_secs(options.timeout as u64))
-				.build()
-				.unwrap(),
-		),
+	let mut builder = CanIConnect::builder()
+		.http_hosts(options.http_hosts)
+		.tcp_hosts(options.tcp_hosts)
+		.targets(options.targets)
+		.max_expansion(options.max_expansion)
+		.timeouts(options.timeouts)
+		.concurrency(options.concurrency)
+		.retry(options.retry)
+		.sampling(options.sampling)
+		.address_mode(options.address_mode)
+		.family(options.family)
+		.source(options.source);
+	if let Some(deadline) = options.deadline {
+		builder = builder.deadline(deadline);
+	}
+	if !options.listen.is_empty() {
+		#[cfg(feature = "server")]
+		{
+			builder = builder.listen(&options.listen);
+		}
+		#[cfg(not(feature = "server"))]
+		{
+			error!("--listen is not available, can-i-connect was built without the `server` feature");
+			return ExitCode::from(EXIT_CONFIG_ERROR);
+		}
+	}
+	let can_i_connect = match builder.build() {
+		Ok(can_i_connect) => can_i_connect,
+		Err(e) => {
+			error!("{}", e);
+			return ExitCode::from(EXIT_CONFIG_ERROR);
+		}
 	};
 
 	// figure out if we are running in server mode (via --listen) or CLI mode
+	#[cfg(feature = "server")]
 	if can_i_connect.server_mode {
 		// we are in server mode
 		info!(
@@ -66,21 +82,189 @@ async fn main() -> Result<()> {
 		});
 		// start up main server
 		can_i_connect.bind().await;
-	} else {
-		// we are in CLI mode
-		let connection_results = can_i_connect.connection_report().await;
-		info!(
-			"Successfully connected to [{}] hosts out of [{}] total hosts",
-			connection_results.successful_hosts.len(),
-			can_i_connect.hosts_total(),
-		);
-		if connection_results.failed_hosts.len() > 0 {
+		return ExitCode::SUCCESS;
+	}
+
+	// the report goes to --output-file or stdout
+	let mut output = match ReportOutput::open(options.output_file.as_deref()) {
+		Ok(output) => output.with_color(options.no_color),
+		Err(e) => {
+			error!("{}", e);
+			return ExitCode::from(EXIT_CONFIG_ERROR);
+		}
+	};
+	// and so do --report-html / --report-md, opened up front so a bad path fails before any host is checked
+	let mut documents = vec![];
+	for (format, path) in &options.documents {
+		match ReportOutput::open(Some(path)) {
+			Ok(document) => documents.push((*format, document)),
+			Err(e) => {
+				error!("{}", e);
+				return ExitCode::from(EXIT_CONFIG_ERROR);
+			}
+		}
+	}
+
+	// we are in wait mode
+	if let Some(wait) = &options.wait {
+		let waited = wait_for(&can_i_connect, &options.success_policy, wait.max_wait).await;
+		let outcome = log_report(&can_i_connect, &waited.report, &options.success_policy);
+		let written = options
+			.output
+			.write(&waited.report, &options.success_policy, &mut output)
+			.and_then(|_| write_documents(&mut documents, &waited.report, &options.success_policy));
+		if let Err(e) = written {
+			error!("{}", e);
+			return ExitCode::from(EXIT_CONFIG_ERROR);
+		}
+		if outcome == Outcome::PolicyFailed {
 			error!(
-				"Failed to connect to the following [{}] host(s): \n[{}]",
-				connection_results.failed_hosts.len(),
-				connection_results.failed_hosts.join("\n")
+				"Gave up waiting for hosts after [{}] round(s) in {:?}",
+				waited.rounds, waited.waited
 			);
+			return ExitCode::from(outcome.exit_code());
 		}
+		info!(
+			"Hosts reachable after [{}] round(s) in {:?}",
+			waited.rounds, waited.waited
+		);
+		return run_command(&wait.command);
+	}
+
+	// we are in CLI mode
+	let (connection_results, written) = options
+		.output
+		.report(&can_i_connect, &options.success_policy, &mut output)
+		.await;
+	let outcome = log_report(&can_i_connect, &connection_results, &options.success_policy);
+	let written = written
+		.and_then(|_| write_documents(&mut documents, &connection_results, &options.success_policy));
+	if let Err(e) = written {
+		error!("{}", e);
+		return ExitCode::from(EXIT_CONFIG_ERROR);
+	}
+	ExitCode::from(outcome.exit_code())
+}
+
+// write every --report-html / --report-md document
+fn write_documents(
+	documents: &mut [(DocumentFormat, ReportOutput)],
+	connection_results: &ConnectionReport,
+	success_policy: &SuccessPolicy,
+) -> Result<()> {
+	let run = RunInfo::current();
+	for (format, document) in documents {
+		document.write(&format.render(connection_results, success_policy, &run))?;
 	}
 	Ok(())
 }
+
+// log what happened to every host and how that measures up to the success policy
+fn log_report(
+	can_i_connect: &CanIConnect,
+	connection_results: &ConnectionReport,
+	success_policy: &SuccessPolicy,
+) -> Outcome {
+	info!(
+		"Successfully connected to [{}] hosts out of [{}] total hosts",
+		connection_results.successful_hosts().len(),
+		can_i_connect.hosts_total(),
+	);
+	let failed_results = connection_results.failed_results();
+	if !failed_results.is_empty() {
+		error!(
+			"Failed to connect to the following [{}] host(s): \n[{}]",
+			failed_results.len(),
+			failed_results
+				.iter()
+				.map(|result| result.to_string())
+				.collect::<Vec<String>>()
+				.join("\n")
+		);
+	}
+	for expansion in connection_results.expansions() {
+		match expansion.failed_hosts_list.is_empty() {
+			true => info!(
+				"[{}/{}] host(s) expanded from {} passed",
+				expansion.hosts_successful, expansion.hosts_total, expansion.spec
+			),
+			false => error!(
+				"[{}/{}] host(s) expanded from {} passed, failed: [{}]",
+				expansion.hosts_successful,
+				expansion.hosts_total,
+				expansion.spec,
+				expansion.failed_hosts_list.join(", ")
+			),
+		}
+	}
+	let blocked_hosts = connection_results.blocked_hosts();
+	if !blocked_hosts.is_empty() {
+		info!(
+			"Correctly blocked [{}] must-fail host(s): [{}]",
+			blocked_hosts.len(),
+			blocked_hosts.join(", ")
+		);
+	}
+	let unexpectedly_reachable = connection_results.unexpectedly_reachable_hosts();
+	if !unexpectedly_reachable.is_empty() {
+		error!(
+			"Unexpectedly reachable [{}] must-fail host(s): [{}]",
+			unexpectedly_reachable.len(),
+			unexpectedly_reachable.join(", ")
+		);
+	}
+	let cancelled_hosts = connection_results.cancelled_hosts();
+	if !cancelled_hosts.is_empty() {
+		error!(
+			"Deadline reached before [{}] host(s) finished: [{}]",
+			cancelled_hosts.len(),
+			cancelled_hosts.join(", ")
+		);
+	}
+	let outcome = Outcome::from_results(success_policy, &connection_results.results);
+	match outcome {
+		Outcome::Success => info!("Success policy [{}] met", success_policy),
+		Outcome::Partial => info!(
+			"Success policy [{}] met, but some hosts were unreachable",
+			success_policy
+		),
+		Outcome::PolicyFailed => error!("Success policy [{}] not met", success_policy),
+	}
+	outcome
+}
+
+// replace can-i-connect with the command `wait` was given
+#[cfg(unix)]
+fn run_command(command: &[String]) -> ExitCode {
+	use std::os::unix::process::CommandExt;
+
+	let Some((program, args)) = command.split_first() else {
+		return ExitCode::SUCCESS;
+	};
+	// only returns when the command could not be started
+	let e = Command::new(program).args(args).exec();
+	error!("Failed to run {}: {}", program, e);
+	command_failed(&e)
+}
+
+// there is no exec outside of unix, run the command and pass its exit code on
+#[cfg(not(unix))]
+fn run_command(command: &[String]) -> ExitCode {
+	let Some((program, args)) = command.split_first() else {
+		return ExitCode::SUCCESS;
+	};
+	match Command::new(program).args(args).status() {
+		Ok(status) => ExitCode::from(status.code().unwrap_or(1) as u8),
+		Err(e) => {
+			error!("Failed to run {}: {}", program, e);
+			command_failed(&e)
+		}
+	}
+}
+
+fn command_failed(e: &io::Error) -> ExitCode {
+	match e.kind() {
+		io::ErrorKind::NotFound => ExitCode::from(EXIT_COMMAND_NOT_FOUND),
+		_ => ExitCode::from(EXIT_COMMAND_FAILED),
+	}
+}
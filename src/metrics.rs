@@ -36,11 +36,12 @@ fn setup_metrics_recorder() -> PrometheusHandle {
 
 pub async fn start_metrics_server(addr: String) {
 	// parse listen_addr as SocketAddr and panic if it fails
-	let socket_addr: SocketAddr = addr
-    .parse()
-    .expect(
-      &format!("Failed to parse socket address '{}'. Please ensure the format is 'IP:PORT' and have valid values", addr)
-    );
+	let socket_addr: SocketAddr = addr.parse().unwrap_or_else(|_| {
+		panic!(
+			"Failed to parse socket address '{}'. Please ensure the format is 'IP:PORT' and have valid values",
+			addr
+		)
+	});
 	let listen_addr = format!("{}:{}", socket_addr.ip(), PROMETHEUS_PORT);
 	let server = metrics_server();
 	let listener = tokio::net::TcpListener::bind(listen_addr).await.unwrap();
@@ -1,10 +1,23 @@
+use crate::can_i_connect::{AddressMode, CanIConnect, DEFAULT_CONCURRENCY};
+use crate::dns::AddressFamily;
 use crate::error::{Error, Result};
-use crate::helpers::{parse_log_level, validate_bind_addr};
+use crate::helpers::{parse_duration, parse_log_level, validate_bind_addr};
+use crate::output::{DocumentFormat, OutputFormat};
+use crate::policy::SuccessPolicy;
+use crate::retry::RetryPolicy;
+use crate::source::Source;
+use crate::stats::Sampling;
+use crate::target::{parse_targets, split_list, Target, DEFAULT_MAX_EXPANSION};
+use crate::timeouts::Timeouts;
+use crate::wait::DEFAULT_MAX_WAIT;
 use clap::ArgMatches;
 use log::LevelFilter;
+use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 
 // region: constants
-pub const DEFAULT_TIMEOUT: usize = 5;
 const DEFAULT_LOG_LEVEL: &str = "info";
 
 // end region: constants
@@ -14,10 +27,34 @@ const DEFAULT_LOG_LEVEL: &str = "info";
 pub struct Options {
 	pub http_hosts: Vec<String>,
 	pub tcp_hosts: Vec<String>,
-	pub timeout: usize,
+	pub targets: Vec<Target>,
+	pub max_expansion: usize,
+	pub timeouts: Timeouts,
+	pub concurrency: usize,
+	pub success_policy: SuccessPolicy,
+	pub output: OutputFormat,
+	// where the report goes, stdout when not set
+	pub output_file: Option<PathBuf>,
+	// report documents written once the report is complete
+	pub documents: Vec<(DocumentFormat, PathBuf)>,
+	pub retry: RetryPolicy,
+	pub sampling: Sampling,
+	pub deadline: Option<Duration>,
+	pub address_mode: AddressMode,
+	pub family: AddressFamily,
+	pub source: Source,
 	pub log_level: LevelFilter,
 	pub no_color: bool,
 	pub listen: String,
+	// set when running `can-i-connect wait`
+	pub wait: Option<WaitOptions>,
+}
+
+#[derive(Debug)]
+pub struct WaitOptions {
+	pub max_wait: Duration,
+	// empty when there is nothing to run once the hosts are reachable
+	pub command: Vec<String>,
 }
 
 // end region: structs
@@ -25,40 +62,168 @@ pub struct Options {
 // region: methods
 impl Options {
 	pub fn from_argc(argc: ArgMatches) -> Result<Options> {
+		match argc.subcommand() {
+			Some(("wait", wait)) => {
+				let mut options = Options::from_matches(wait)?;
+				options.wait = Some(WaitOptions::from_argc(wait)?);
+				Ok(options)
+			}
+			_ => Options::from_matches(&argc),
+		}
+	}
+
+	fn from_matches(argc: &ArgMatches) -> Result<Options> {
 		let http_hosts = match argc.get_one::<String>("http-hosts") {
-			Some(hosts) => hosts
-				.split(",")
-				.map(|el| el.to_string())
-				.collect::<Vec<String>>(),
+			Some(hosts) => split_list(hosts),
 			None => {
 				vec![]
 			}
 		};
 		let tcp_hosts = match argc.get_one::<String>("tcp-hosts") {
-			Some(hosts) => hosts
-				.split(",")
-				.map(|el| el.to_string())
-				.collect::<Vec<String>>(),
+			Some(hosts) => split_list(hosts),
 			None => {
 				vec![]
 			}
 		};
-		let timeout = match argc.get_one::<String>("timeout") {
-			None => DEFAULT_TIMEOUT,
-			Some(timeout) => timeout
-				.parse::<usize>()
-				.map_err(|_| Error::InvalidTimeout(timeout.to_string()))?,
+		// every invalid host is reported at once, once the expansion cap is known
+		let (targets, mut problems) = match argc.get_one::<String>("targets") {
+			Some(targets) => parse_targets(&split_list(targets)),
+			None => (vec![], vec![]),
+		};
+		let max_expansion = match argc.get_one::<String>("max-expansion") {
+			None => DEFAULT_MAX_EXPANSION,
+			Some(max_expansion) => match max_expansion.parse::<usize>() {
+				Ok(n) if n > 0 => n,
+				_ => return Err(Error::InvalidMaxExpansion(max_expansion.to_string())),
+			},
 		};
+		let hosts = CanIConnect {
+			http: http_hosts.clone(),
+			tcp: tcp_hosts.clone(),
+			targets: targets.clone(),
+			max_expansion,
+			..Default::default()
+		};
+		problems.extend(hosts.problems());
+		Error::from_problems(problems)?;
+		let mut timeouts = Timeouts::default();
+		if let Some(timeout) = argc.get_one::<String>("timeout") {
+			timeouts.total = parse_duration(timeout)
+				.ok_or_else(|| Error::InvalidDuration(String::from("--timeout"), timeout.to_string()))?;
+		}
+		if let Some(timeout) = argc.get_one::<String>("connect-timeout") {
+			timeouts.connect = Some(parse_duration(timeout).ok_or_else(|| {
+				Error::InvalidDuration(String::from("--connect-timeout"), timeout.to_string())
+			})?);
+		}
+		if let Some(timeout) = argc.get_one::<String>("tls-timeout") {
+			timeouts.tls = Some(parse_duration(timeout).ok_or_else(|| {
+				Error::InvalidDuration(String::from("--tls-timeout"), timeout.to_string())
+			})?);
+		}
+		let concurrency = match argc.get_one::<String>("concurrency") {
+			None => DEFAULT_CONCURRENCY,
+			Some(concurrency) => match concurrency.parse::<usize>() {
+				Ok(n) if n > 0 => n,
+				_ => return Err(Error::InvalidConcurrency(concurrency.to_string())),
+			},
+		};
+		let success_policy = match argc.get_one::<String>("success-policy") {
+			None => SuccessPolicy::default(),
+			Some(policy) => policy.parse::<SuccessPolicy>()?,
+		};
+		let output = match argc.get_one::<String>("output") {
+			None => OutputFormat::default(),
+			Some(output) => output.parse::<OutputFormat>()?,
+		};
+		let output_file = argc.get_one::<String>("output-file").map(PathBuf::from);
+		if output_file.is_some() && !output.writes_report() {
+			return Err(Error::OutputFileWithoutFormat);
+		}
+		let mut documents = vec![];
+		for (arg, format) in [
+			("report-html", DocumentFormat::Html),
+			("report-md", DocumentFormat::Markdown),
+		] {
+			if let Some(path) = argc.get_one::<String>(arg) {
+				documents.push((format, PathBuf::from(path)));
+			}
+		}
+		let mut retry = RetryPolicy::default();
+		if let Some(retries) = argc.get_one::<String>("retries") {
+			retry.retries = retries
+				.parse::<u32>()
+				.map_err(|_| Error::InvalidRetries(retries.to_string()))?;
+		}
+		if let Some(backoff) = argc.get_one::<String>("retry-backoff") {
+			retry.backoff = parse_duration(backoff).ok_or_else(|| {
+				Error::InvalidDuration(String::from("--retry-backoff"), backoff.to_string())
+			})?;
+		}
+		if let Some(max_delay) = argc.get_one::<String>("retry-max-delay") {
+			retry.max_delay = parse_duration(max_delay).ok_or_else(|| {
+				Error::InvalidDuration(String::from("--retry-max-delay"), max_delay.to_string())
+			})?;
+		}
+		let mut sampling = Sampling::default();
+		if let Some(samples) = argc.get_one::<String>("samples") {
+			sampling.samples = match samples.parse::<u32>() {
+				Ok(n) if n > 0 => n,
+				_ => return Err(Error::InvalidSamples(samples.to_string())),
+			};
+		}
+		if let Some(interval) = argc.get_one::<String>("sample-interval") {
+			sampling.interval = parse_duration(interval).ok_or_else(|| {
+				Error::InvalidDuration(String::from("--sample-interval"), interval.to_string())
+			})?;
+		}
+		let deadline =
+			match argc.get_one::<String>("deadline") {
+				None => None,
+				Some(deadline) => Some(parse_duration(deadline).ok_or_else(|| {
+					Error::InvalidDuration(String::from("--deadline"), deadline.to_string())
+				})?),
+			};
+		// clap makes sure these two are not used together
+		let address_mode = if argc.get_flag("all-addresses") {
+			AddressMode::All
+		} else if argc.get_flag("happy-eyeballs") {
+			AddressMode::HappyEyeballs
+		} else {
+			AddressMode::First
+		};
+		// clap makes sure only one of the family flags is used
+		let family = if argc.get_flag("ipv4-only") {
+			AddressFamily::Ipv4Only
+		} else if argc.get_flag("ipv6-only") {
+			AddressFamily::Ipv6Only
+		} else if argc.get_flag("prefer-ipv6") {
+			AddressFamily::PreferIpv6
+		} else {
+			AddressFamily::PreferIpv4
+		};
+		let mut source = Source::default();
+		if let Some(address) = argc.get_one::<String>("source-address") {
+			source.address = Some(
+				address
+					.parse::<IpAddr>()
+					.map_err(|_| Error::InvalidSourceAddress(address.to_string()))?,
+			);
+		}
+		source.interface = argc.get_one::<String>("interface").cloned();
 		let level = match argc.get_one::<String>("log-level") {
 			Some(level) => String::from(level),
 			None => String::from(DEFAULT_LOG_LEVEL),
 		};
 		let log_level = parse_log_level(&level)?;
-		let no_color = argc.get_flag("no-color");
+		// --no-color is a SetFalse flag, so this is true when colour is wanted. NO_COLOR turns it off too
+		let no_color =
+			argc.get_flag("no-color") && env::var_os("NO_COLOR").unwrap_or_default().is_empty();
 
-		let listen = match argc.get_one::<String>("listen") {
+		// `wait` does not take --listen
+		let listen = match argc.try_get_one::<String>("listen").ok().flatten() {
 			Some(bind_addr) => {
-				let bind_addr = validate_bind_addr(bind_addr).map_err(|e| e);
+				let bind_addr = validate_bind_addr(bind_addr);
 				match bind_addr {
 					Ok(addr) => addr.to_string(),
 					Err(e) => return Err(e),
@@ -68,19 +233,48 @@ impl Options {
 		};
 
 		// throw if there are 0 hosts specified and user did not specify to run in server mode via --listen
-		if (http_hosts.len() == 0 && tcp_hosts.len() == 0) && listen.is_empty() {
+		if (http_hosts.is_empty() && tcp_hosts.is_empty() && targets.is_empty()) && listen.is_empty() {
 			return Err(Error::NoHostsSupplied);
 		}
 
 		Ok(Options {
 			http_hosts,
 			tcp_hosts,
-			timeout,
+			targets,
+			max_expansion,
+			timeouts,
+			concurrency,
+			success_policy,
+			output,
+			output_file,
+			documents,
+			retry,
+			sampling,
+			deadline,
+			address_mode,
+			family,
+			source,
 			log_level,
 			no_color,
 			listen,
+			wait: None,
 		})
 	}
 }
 
+impl WaitOptions {
+	pub fn from_argc(argc: &ArgMatches) -> Result<WaitOptions> {
+		let max_wait = match argc.get_one::<String>("max-wait") {
+			None => DEFAULT_MAX_WAIT,
+			Some(max_wait) => parse_duration(max_wait)
+				.ok_or_else(|| Error::InvalidDuration(String::from("--max-wait"), max_wait.to_string()))?,
+		};
+		let command = match argc.get_many::<String>("command") {
+			Some(command) => command.cloned().collect::<Vec<String>>(),
+			None => vec![],
+		};
+		Ok(WaitOptions { max_wait, command })
+	}
+}
+
 // end region: methods
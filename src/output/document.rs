@@ -0,0 +1,197 @@
+use super::RunInfo;
+use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+use crate::policy::{Outcome, SuccessPolicy};
+use crate::stats::LatencyStats;
+use std::time::Duration;
+
+// region: constants
+pub const RESULT_HEADERS: [&str; 7] = [
+	"target", "protocol", "tag", "status", "address", "latency", "attempts",
+];
+// position of the status in a result row
+pub const STATUS_COLUMN: usize = 3;
+pub const STATS_HEADERS: [&str; 10] = [
+	"target", "samples", "received", "loss", "min", "mean", "p50", "p95", "max", "jitter",
+];
+
+// endregion: constants
+
+// region: functions
+// what the HTML and Markdown reports share: the run, the results, latency stats and why hosts failed.
+// `escape` is shared with the JUnit report too
+pub fn summary(
+	report: &ConnectionReport,
+	success_policy: &SuccessPolicy,
+	run: &RunInfo,
+) -> Vec<(&'static str, String)> {
+	let mut hosts = vec![
+		format!("{} total", report.results.len()),
+		format!("{} successful", report.successful_hosts().len()),
+		format!("{} failed", report.failed_hosts().len()),
+	];
+	for (count, label) in [
+		(report.cancelled_hosts().len(), "cancelled"),
+		(report.blocked_hosts().len(), "correctly blocked"),
+		(
+			report.unexpectedly_reachable_hosts().len(),
+			"unexpectedly reachable",
+		),
+	] {
+		if count > 0 {
+			hosts.push(format!("{} {}", count, label));
+		}
+	}
+	vec![
+		("version", run.version.clone()),
+		("host", run.hostname.clone()),
+		(
+			"generated at",
+			humantime::format_rfc3339_seconds(run.generated_at).to_string(),
+		),
+		("success policy", success_policy.to_string()),
+		("outcome", outcome_label(outcome(report, success_policy))),
+		("hosts", hosts.join(", ")),
+	]
+}
+
+pub fn outcome(report: &ConnectionReport, success_policy: &SuccessPolicy) -> Outcome {
+	Outcome::from_results(success_policy, &report.results)
+}
+
+pub fn outcome_label(outcome: Outcome) -> String {
+	match outcome {
+		Outcome::Success => String::from("success"),
+		Outcome::Partial => {
+			String::from("partial, the success policy was met but some hosts were unreachable")
+		}
+		Outcome::PolicyFailed => String::from("failed, the success policy was not met"),
+	}
+}
+
+pub fn result_row(result: &ProbeResult) -> [String; 7] {
+	let target = match result.must_fail {
+		true => format!("!{}", result.target),
+		false => result.target.clone(),
+	};
+	[
+		target,
+		result.protocol.clone(),
+		result.tag.clone().unwrap_or_default(),
+		result.status.to_string(),
+		result
+			.resolved_ip
+			.map(|ip| ip.to_string())
+			.unwrap_or_default(),
+		ms(result.latency),
+		result.attempts.to_string(),
+	]
+}
+
+pub fn stats_row(target: &str, stats: &LatencyStats) -> [String; 10] {
+	let ms = |duration: Option<Duration>| duration.map(ms).unwrap_or_else(|| String::from("-"));
+	[
+		target.to_string(),
+		stats.samples.to_string(),
+		stats.received.to_string(),
+		format!("{:.1}%", stats.loss_percent),
+		ms(stats.min),
+		ms(stats.mean),
+		ms(stats.p50),
+		ms(stats.p95),
+		ms(stats.max),
+		ms(stats.jitter),
+	]
+}
+
+// hosts that failed the check, unreachable hosts and reachable must-fail hosts
+pub fn failures(report: &ConnectionReport) -> Vec<&ProbeResult> {
+	report
+		.results
+		.iter()
+		.filter(|result| !result.success)
+		.collect()
+}
+
+// why a host failed, one line per fact
+pub fn failure_reasons(result: &ProbeResult) -> Vec<(&'static str, String)> {
+	let mut reasons = vec![("status", result.status.to_string())];
+	match result.status {
+		ProbeStatus::UnexpectedlyReachable => reasons.push((
+			"error",
+			String::from("connected, but the host must not be reachable"),
+		)),
+		_ => {
+			if let Some(kind) = result.error_kind {
+				reasons.push(("error kind", kind.to_string()));
+			}
+			reasons.push((
+				"error",
+				result
+					.error
+					.clone()
+					.unwrap_or_else(|| String::from("connection failed")),
+			));
+		}
+	}
+	reasons.push(("latency", ms(result.latency)));
+	// a single attempt failed with the error above
+	for attempt in result.attempt_errors.iter().filter(|_| result.attempts > 1) {
+		reasons.push((
+			"attempt",
+			format!(
+				"{}: {} ({})",
+				attempt.attempt, attempt.error, attempt.error_kind
+			),
+		));
+	}
+	for address in &result.addresses {
+		reasons.push(("address", address.to_string()));
+	}
+	reasons
+}
+
+// text made safe for XML and HTML. Characters XML 1.0 does not allow (control characters other than
+// tab, newline and carriage return) are dropped, an error message could carry them from the network
+pub fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			'\t' | '\n' | '\r' => escaped.push(c),
+			'\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => {}
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+fn ms(duration: Duration) -> String {
+	format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::escape;
+
+	#[test]
+	fn escape_test() {
+		assert_eq!(
+			escape("<a href=\"x\">Tom & Jerry's</a>"),
+			"&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+		);
+		// not allowed in XML 1.0, even as character references
+		assert_eq!(
+			escape("a\u{0}b\u{8}c\u{b}\u{c}d\u{1b}[0m\u{ffff}"),
+			"abcd[0m"
+		);
+		assert_eq!(escape("tab\tnew\nline\r"), "tab\tnew\nline\r");
+	}
+}
+// endregion: unit tests
@@ -0,0 +1,180 @@
+use super::document::{
+	escape, failure_reasons, failures, outcome, result_row, stats_row, summary, RESULT_HEADERS,
+	STATS_HEADERS, STATUS_COLUMN,
+};
+use super::RunInfo;
+use crate::can_i_connect::{ConnectionReport, ProbeStatus};
+use crate::policy::{Outcome, SuccessPolicy};
+
+// region: constants
+// everything the page needs is inline, so the file opens offline
+const STYLE: &str =
+	"body{font-family:system-ui,-apple-system,'Segoe UI',sans-serif;margin:2rem;color:#1f2328}\
+table{border-collapse:collapse;margin-bottom:1.5rem}\
+th,td{border:1px solid #d0d7de;padding:.3rem .6rem;text-align:left;vertical-align:top}\
+th{background:#f6f8fa}\
+.success,.blocked{color:#1a7f37}\
+.failed,.unexpectedly-reachable{color:#cf222e}\
+.cancelled,.partial{color:#9a6700}\
+.outcome{font-size:1.2rem;font-weight:bold}\
+section{margin-bottom:1rem}\
+dt{font-weight:bold;float:left;clear:left;width:7rem}\
+dd{margin-left:8rem}";
+
+// endregion: constants
+
+// region: functions
+// the report as a single HTML page with no external assets
+pub fn render(report: &ConnectionReport, success_policy: &SuccessPolicy, run: &RunInfo) -> String {
+	let mut html = format!(
+		"<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>can-i-connect report</title>\n<style>{}</style>\n</head>\n<body>\n<h1>can-i-connect report</h1>\n",
+		STYLE
+	);
+	let outcome_class = match outcome(report, success_policy) {
+		Outcome::Success => "success",
+		Outcome::Partial => "partial",
+		Outcome::PolicyFailed => "failed",
+	};
+	html.push_str("<table class=\"summary\">\n");
+	for (name, value) in summary(report, success_policy, run) {
+		let class = match name {
+			"outcome" => format!(" class=\"outcome {}\"", outcome_class),
+			_ => String::new(),
+		};
+		html.push_str(&format!(
+			"<tr><th>{}</th><td{}>{}</td></tr>\n",
+			escape(name),
+			class,
+			escape(&value)
+		));
+	}
+	html.push_str("</table>\n");
+
+	html.push_str("<h2>Results</h2>\n<table>\n");
+	html.push_str(&header_row(&RESULT_HEADERS));
+	for result in &report.results {
+		let cells = result_row(result)
+			.iter()
+			.enumerate()
+			.map(|(column, cell)| match column {
+				STATUS_COLUMN => format!(
+					"<td class=\"{}\">{}</td>",
+					status_class(result.status),
+					escape(cell)
+				),
+				_ => format!("<td>{}</td>", escape(cell)),
+			})
+			.collect::<String>();
+		html.push_str(&format!("<tr>{}</tr>\n", cells));
+	}
+	html.push_str("</table>\n");
+
+	let stats = report
+		.results
+		.iter()
+		.filter_map(|result| Some(stats_row(&result.target, result.stats.as_ref()?)))
+		.collect::<Vec<[String; 10]>>();
+	if !stats.is_empty() {
+		html.push_str("<h2>Latency stats</h2>\n<table>\n");
+		html.push_str(&header_row(&STATS_HEADERS));
+		for row in stats {
+			let cells = row
+				.iter()
+				.map(|cell| format!("<td>{}</td>", escape(cell)))
+				.collect::<String>();
+			html.push_str(&format!("<tr>{}</tr>\n", cells));
+		}
+		html.push_str("</table>\n");
+	}
+
+	let failures = failures(report);
+	if !failures.is_empty() {
+		html.push_str("<h2>Failures</h2>\n");
+		for result in failures {
+			html.push_str(&format!(
+				"<section>\n<h3>{} [{}]</h3>\n<dl>\n",
+				escape(&result.target),
+				escape(&result.protocol)
+			));
+			for (name, reason) in failure_reasons(result) {
+				html.push_str(&format!(
+					"<dt>{}</dt><dd>{}</dd>\n",
+					escape(name),
+					escape(&reason)
+				));
+			}
+			html.push_str("</dl>\n</section>\n");
+		}
+	}
+	html.push_str("</body>\n</html>\n");
+	html
+}
+
+fn header_row(headers: &[&str]) -> String {
+	let cells = headers
+		.iter()
+		.map(|header| format!("<th>{}</th>", header))
+		.collect::<String>();
+	format!("<tr>{}</tr>\n", cells)
+}
+
+fn status_class(status: ProbeStatus) -> &'static str {
+	match status {
+		ProbeStatus::Success => "success",
+		ProbeStatus::Failed => "failed",
+		ProbeStatus::Cancelled => "cancelled",
+		ProbeStatus::Blocked => "blocked",
+		ProbeStatus::UnexpectedlyReachable => "unexpectedly-reachable",
+	}
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::render;
+	use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+	use crate::error::Error;
+	use crate::output::RunInfo;
+	use crate::policy::SuccessPolicy;
+	use crate::stats::LatencyStats;
+	use std::time::{Duration, SystemTime};
+
+	#[test]
+	fn html_test() {
+		let run = RunInfo {
+			version: String::from("0.2.2-abc1234"),
+			hostname: String::from("build-7"),
+			generated_at: SystemTime::now(),
+		};
+		let mut reachable = ProbeResult::new("db:5432", "tcp");
+		reachable.status = ProbeStatus::Success;
+		reachable.success = true;
+		reachable.stats = Some(LatencyStats::from_samples(&[
+			Some(Duration::from_millis(1)),
+			Some(Duration::from_millis(2)),
+			Some(Duration::from_millis(3)),
+		]));
+		let mut refused = ProbeResult::new("<cache>:6379", "tcp");
+		refused.attempts = 2;
+		refused.set_error(&Error::ConnectionRefused(String::from("<cache>:6379")));
+		let report = ConnectionReport {
+			results: vec![reachable, refused],
+		};
+
+		let html = render(&report, &SuccessPolicy::Any, &run);
+		assert!(html.starts_with("<!DOCTYPE html>"));
+		assert!(html.ends_with("</html>\n"));
+		// a single file that opens offline
+		assert!(!html.contains("<link") && !html.contains("<script") && !html.contains("src="));
+		assert!(html.contains("<tr><th>host</th><td>build-7</td></tr>"));
+		assert!(html.contains("<td class=\"outcome partial\">"));
+		assert!(html.contains("<td class=\"failed\">failed</td>"));
+		assert!(html.contains("<h2>Latency stats</h2>"));
+		assert!(html.contains("<td>db:5432</td><td>3</td><td>3</td><td>0.0%</td><td>1.0ms</td>"));
+		assert!(html.contains("<h3>&lt;cache&gt;:6379 [tcp]</h3>"));
+		assert!(html.contains("<dt>error kind</dt><dd>connection_refused</dd>"));
+	}
+}
+// endregion: unit tests
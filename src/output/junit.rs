@@ -0,0 +1,167 @@
+use super::document::escape;
+use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+// region: functions
+// the report as JUnit XML, one test case per host. Tagged hosts are grouped into a test suite per
+// tag, the others into a test suite per protocol
+pub fn render(report: &ConnectionReport) -> String {
+	let mut suites: Vec<(String, Vec<&ProbeResult>)> = vec![];
+	for result in &report.results {
+		let name = suite_name(result);
+		match suites.iter_mut().find(|(suite, _)| *suite == name) {
+			Some((_, results)) => results.push(result),
+			None => suites.push((name, vec![result])),
+		}
+	}
+	let all = report.results.iter().collect::<Vec<&ProbeResult>>();
+	let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	let _ = writeln!(
+		xml,
+		"<testsuites name=\"can-i-connect\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\">",
+		all.len(),
+		failures(&all),
+		elapsed(&all).as_secs_f64()
+	);
+	for (name, results) in &suites {
+		write_suite(&mut xml, name, results);
+	}
+	xml.push_str("</testsuites>\n");
+	xml
+}
+
+fn suite_name(result: &ProbeResult) -> String {
+	match &result.tag {
+		Some(tag) => tag.clone(),
+		None => result.protocol.clone(),
+	}
+}
+
+fn write_suite(xml: &mut String, name: &str, results: &[&ProbeResult]) {
+	let started_at = results
+		.iter()
+		.map(|result| result.started_at)
+		.min()
+		.unwrap_or_else(SystemTime::now);
+	let _ = writeln!(
+		xml,
+		"  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{:.3}\" timestamp=\"{}\">",
+		escape(name),
+		results.len(),
+		failures(results),
+		elapsed(results).as_secs_f64(),
+		humantime::format_rfc3339_millis(started_at)
+	);
+	for result in results {
+		write_case(xml, name, result);
+	}
+	xml.push_str("  </testsuite>\n");
+}
+
+fn write_case(xml: &mut String, suite: &str, result: &ProbeResult) {
+	let name = match result.must_fail {
+		true => format!("{} (must fail)", result.target),
+		false => result.target.clone(),
+	};
+	let _ = write!(
+		xml,
+		"    <testcase name=\"{}\" classname=\"can-i-connect.{}\" time=\"{:.3}\"",
+		escape(&name),
+		escape(suite),
+		result.latency.as_secs_f64()
+	);
+	if result.success {
+		xml.push_str("/>\n");
+		return;
+	}
+	let latency = result.latency.as_secs_f64() * 1000.0;
+	let (kind, message) = match result.status {
+		ProbeStatus::UnexpectedlyReachable => (
+			String::from("unexpectedly_reachable"),
+			format!(
+				"connected in {:.1}ms, but the host must not be reachable",
+				latency
+			),
+		),
+		_ => (
+			result
+				.error_kind
+				.map(|kind| kind.to_string())
+				.unwrap_or_else(|| String::from("failed")),
+			format!(
+				"{} after {:.1}ms",
+				result.error.as_deref().unwrap_or("connection failed"),
+				latency
+			),
+		),
+	};
+	let _ = writeln!(
+		xml,
+		">\n      <failure type=\"{}\" message=\"{}\">{}</failure>\n    </testcase>",
+		escape(&kind),
+		escape(&message),
+		escape(&result.to_string())
+	);
+}
+
+fn failures(results: &[&ProbeResult]) -> usize {
+	results.iter().filter(|result| !result.success).count()
+}
+
+// wall-clock time from the first host starting to the last one finishing
+fn elapsed(results: &[&ProbeResult]) -> Duration {
+	let started_at = results.iter().map(|result| result.started_at).min();
+	let finished_at = results.iter().map(|result| result.finished_at).max();
+	match (started_at, finished_at) {
+		(Some(started_at), Some(finished_at)) => {
+			finished_at.duration_since(started_at).unwrap_or_default()
+		}
+		_ => Duration::ZERO,
+	}
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::render;
+	use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+	use crate::error::Error;
+	use std::time::Duration;
+
+	#[test]
+	fn junit_test() {
+		let mut reachable = ProbeResult::new("db:5432", "tcp");
+		reachable.tag = Some(String::from("db"));
+		reachable.status = ProbeStatus::Success;
+		reachable.success = true;
+		let mut refused = ProbeResult::new("cache:6379", "tcp");
+		refused.latency = Duration::from_millis(12);
+		refused.set_error(&Error::ConnectionRefused(String::from("cache:6379")));
+		let mut http = ProbeResult::new("https://example.com/?a=1&b=<2>\u{1b}[0m", "https");
+		http.status = ProbeStatus::Success;
+		http.success = true;
+		let report = ConnectionReport {
+			results: vec![reachable, refused, http],
+		};
+
+		let xml = render(&report);
+		assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"can-i-connect\" tests=\"3\" failures=\"1\""));
+		assert!(xml.contains("<testsuite name=\"db\" tests=\"1\" failures=\"0\""));
+		assert!(xml.contains("<testsuite name=\"tcp\" tests=\"1\" failures=\"1\""));
+		assert!(xml.contains("<testsuite name=\"https\" tests=\"1\" failures=\"0\""));
+		assert!(
+			xml.contains("<testcase name=\"db:5432\" classname=\"can-i-connect.db\" time=\"0.000\"/>")
+		);
+		assert!(
+			xml.contains("<testcase name=\"cache:6379\" classname=\"can-i-connect.tcp\" time=\"0.012\">")
+		);
+		assert!(xml.contains("<failure type=\"connection_refused\" message=\"connection to cache:6379 was refused after 12.0ms\">"));
+		// the escape character is not allowed in XML 1.0
+		assert!(xml.contains("name=\"https://example.com/?a=1&amp;b=&lt;2&gt;[0m\""));
+		assert!(xml.ends_with("</testsuites>\n"));
+	}
+}
+// endregion: unit tests
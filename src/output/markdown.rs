@@ -0,0 +1,136 @@
+use super::document::{
+	escape, failure_reasons, failures, result_row, stats_row, summary, RESULT_HEADERS, STATS_HEADERS,
+};
+use super::RunInfo;
+use crate::can_i_connect::ConnectionReport;
+use crate::policy::SuccessPolicy;
+
+// region: functions
+// the report as a Markdown document, to attach to tickets
+pub fn render(report: &ConnectionReport, success_policy: &SuccessPolicy, run: &RunInfo) -> String {
+	let mut markdown = String::from("# can-i-connect report\n\n");
+	markdown.push_str(&table(
+		&["", ""],
+		summary(report, success_policy, run)
+			.into_iter()
+			.map(|(name, value)| vec![format!("**{}**", name), escape_cell(&value)]),
+	));
+
+	markdown.push_str("\n## Results\n\n");
+	markdown.push_str(&table(
+		&RESULT_HEADERS,
+		report
+			.results
+			.iter()
+			.map(|result| escape_row(&result_row(result))),
+	));
+
+	let stats = report
+		.results
+		.iter()
+		.filter_map(|result| {
+			Some(escape_row(&stats_row(
+				&result.target,
+				result.stats.as_ref()?,
+			)))
+		})
+		.collect::<Vec<Vec<String>>>();
+	if !stats.is_empty() {
+		markdown.push_str("\n## Latency stats\n\n");
+		markdown.push_str(&table(&STATS_HEADERS, stats));
+	}
+
+	let failures = failures(report);
+	if !failures.is_empty() {
+		markdown.push_str("\n## Failures\n");
+		for result in failures {
+			markdown.push_str(&format!(
+				"\n### {} [{}]\n\n",
+				escape_cell(&result.target),
+				escape_cell(&result.protocol)
+			));
+			for (name, reason) in failure_reasons(result) {
+				markdown.push_str(&format!("- **{}:** {}\n", name, escape_cell(&reason)));
+			}
+		}
+	}
+	markdown
+}
+
+// rows are escaped by the caller, so cells can hold markdown
+fn table(headers: &[&str], rows: impl IntoIterator<Item = Vec<String>>) -> String {
+	let mut table = format!("| {} |\n", headers.join(" | "));
+	table.push_str(&format!("|{}\n", "---|".repeat(headers.len())));
+	for row in rows {
+		table.push_str(&format!("| {} |\n", row.join(" | ")));
+	}
+	table
+}
+
+fn escape_row(row: &[String]) -> Vec<String> {
+	row.iter().map(|cell| escape_cell(cell)).collect()
+}
+
+// markdown renders inline HTML, so text is escaped as for the HTML report. Emphasis and code
+// characters are escaped so targets and errors show as written, and cells stay on one line, `|`
+// would start a new cell
+fn escape_cell(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in escape(text).chars() {
+		match c {
+			'\\' | '*' | '_' | '`' | '|' => {
+				escaped.push('\\');
+				escaped.push(c);
+			}
+			'\n' => escaped.push_str("<br>"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::render;
+	use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+	use crate::error::Error;
+	use crate::output::RunInfo;
+	use crate::policy::SuccessPolicy;
+	use std::time::{Duration, UNIX_EPOCH};
+
+	#[test]
+	fn markdown_test() {
+		let run = RunInfo {
+			version: String::from("0.2.2-abc1234"),
+			hostname: String::from("build-7"),
+			generated_at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+		};
+		let mut reachable = ProbeResult::new("db:5432", "tcp");
+		reachable.tag = Some(String::from("db"));
+		reachable.status = ProbeStatus::Success;
+		reachable.success = true;
+		reachable.attempts = 1;
+		let mut refused = ProbeResult::new("https://example.com/a|b?c=<d>&e=*f*_g_`h`", "https");
+		refused.attempts = 1;
+		refused.set_error(&Error::ConnectionRefused(String::from("example.com:443")));
+		let report = ConnectionReport {
+			results: vec![reachable, refused],
+		};
+
+		let markdown = render(&report, &SuccessPolicy::All, &run);
+		assert!(markdown.starts_with("# can-i-connect report\n"));
+		assert!(markdown.contains("| **version** | 0.2.2-abc1234 |\n| **host** | build-7 |\n| **generated at** | 2023-11-14T22:13:20Z |"));
+		assert!(markdown.contains("| **hosts** | 2 total, 1 successful, 1 failed |"));
+		assert!(markdown.contains("| db:5432 | tcp | db | success |  | 0.0ms | 1 |"));
+		// `|` would end the cell early, `<d>` would be rendered as HTML and `*f*` as emphasis
+		assert!(markdown.contains(
+			"| https://example.com/a\\|b?c=&lt;d&gt;&amp;e=\\*f\\*\\_g\\_\\`h\\` | https |  | failed |"
+		));
+		assert!(!markdown.contains("## Latency stats"));
+		assert!(markdown.contains("### https://example.com/a\\|b?c=&lt;d&gt;&amp;e=\\*f\\*\\_g\\_\\`h\\` [https]\n\n- **status:** failed\n- **error kind:** connection\\_refused\n- **error:** connection to example.com:443 was refused\n- **latency:** 0.0ms\n"));
+	}
+}
+// endregion: unit tests
@@ -0,0 +1,253 @@
+use crate::can_i_connect::{CanIConnect, ConnectionReport};
+use crate::error::{Error, Result};
+use crate::helpers::hostname;
+#[cfg(feature = "cli")]
+use crate::helpers::terminal_width;
+use crate::policy::SuccessPolicy;
+use crate::version::VERSION;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+// modules
+mod document;
+pub mod html;
+pub mod junit;
+pub mod markdown;
+pub mod table;
+pub mod tap;
+
+// region: enums
+// how the results of a CLI run are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+	// log lines only
+	#[default]
+	Log,
+	// the whole report on stdout, with the same schema as the `/can-i-connect` response
+	Json,
+	// a JUnit XML report, one test case per host
+	Junit,
+	// Test Anything Protocol, one line per host written as soon as it finishes
+	Tap,
+	// an aligned table for people, one row per host
+	Table,
+}
+
+// report documents, written to a file once the report is complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+	// a single page with no external assets, so it opens offline
+	Html,
+	Markdown,
+}
+
+// endregion: enums
+
+// region: structs
+// where the report is written: a file or stdout
+pub struct ReportOutput {
+	name: String,
+	writer: Box<dyn Write + Send>,
+	// stdout is a terminal, so the report can be coloured and fit to its width
+	terminal: bool,
+	color: bool,
+}
+
+// the run a report document describes
+#[derive(Debug, Clone)]
+pub struct RunInfo {
+	pub version: String,
+	pub hostname: String,
+	pub generated_at: SystemTime,
+}
+
+// endregion: structs
+
+// region: methods
+impl OutputFormat {
+	// logs move to stderr when stdout carries the report
+	pub fn writes_report(&self) -> bool {
+		*self != OutputFormat::Log
+	}
+	// the report in this format, nothing for `Log` as every result has already been logged
+	pub fn render(
+		&self,
+		report: &ConnectionReport,
+		success_policy: &SuccessPolicy,
+	) -> Option<String> {
+		match self {
+			OutputFormat::Log => None,
+			OutputFormat::Json => serde_json::to_string_pretty(&report.to_json(success_policy)).ok(),
+			OutputFormat::Junit => Some(junit::render(report)),
+			OutputFormat::Tap => Some(tap::render(report)),
+			OutputFormat::Table => Some(table::render(report, success_policy, false, None)),
+		}
+	}
+	// check every host and write the report. Streaming formats are written host by host as they
+	// finish, the others once the report is complete. The report is returned even if writing failed
+	pub async fn report(
+		&self,
+		can_i_connect: &CanIConnect,
+		success_policy: &SuccessPolicy,
+		output: &mut ReportOutput,
+	) -> (ConnectionReport, Result<()>) {
+		match self {
+			OutputFormat::Tap => tap::stream(can_i_connect, output).await,
+			_ => {
+				let report = can_i_connect.connection_report().await;
+				let written = self.write(&report, success_policy, output);
+				(report, written)
+			}
+		}
+	}
+	// write the whole report at once
+	pub fn write(
+		&self,
+		report: &ConnectionReport,
+		success_policy: &SuccessPolicy,
+		output: &mut ReportOutput,
+	) -> Result<()> {
+		if *self == OutputFormat::Table {
+			#[cfg(feature = "cli")]
+			let width = output.terminal.then(terminal_width).flatten();
+			// only the CLI fits the table to the terminal
+			#[cfg(not(feature = "cli"))]
+			let width = None;
+			let rendered = table::render(report, success_policy, output.color, width);
+			return output.write(&rendered);
+		}
+		match self.render(report, success_policy) {
+			Some(rendered) if rendered.ends_with('\n') => output.write(&rendered),
+			Some(rendered) => output.write(&(rendered + "\n")),
+			None => Ok(()),
+		}
+	}
+}
+
+impl DocumentFormat {
+	pub fn render(
+		&self,
+		report: &ConnectionReport,
+		success_policy: &SuccessPolicy,
+		run: &RunInfo,
+	) -> String {
+		match self {
+			DocumentFormat::Html => html::render(report, success_policy, run),
+			DocumentFormat::Markdown => markdown::render(report, success_policy, run),
+		}
+	}
+}
+
+impl RunInfo {
+	// this build, on this machine, right now
+	pub fn current() -> RunInfo {
+		RunInfo {
+			version: VERSION.to_string(),
+			hostname: hostname(),
+			generated_at: SystemTime::now(),
+		}
+	}
+}
+
+impl ReportOutput {
+	// `path`, or stdout when there is none. Opened before any host is checked so a bad path fails
+	// fast
+	pub fn open(path: Option<&Path>) -> Result<ReportOutput> {
+		match path {
+			Some(path) => {
+				let name = path.display().to_string();
+				let file =
+					File::create(path).map_err(|e| Error::ReportWriteFailed(name.clone(), e.to_string()))?;
+				Ok(ReportOutput {
+					name,
+					writer: Box::new(file),
+					terminal: false,
+					color: false,
+				})
+			}
+			None => Ok(ReportOutput {
+				name: String::from("stdout"),
+				writer: Box::new(io::stdout()),
+				terminal: io::stdout().is_terminal(),
+				color: false,
+			}),
+		}
+	}
+	// colour the report, only ever applied on a terminal
+	pub fn with_color(mut self, color: bool) -> ReportOutput {
+		self.color = color && self.terminal;
+		self
+	}
+	// written straight through, so streamed lines show up as they happen
+	pub fn write(&mut self, text: &str) -> Result<()> {
+		self
+			.writer
+			.write_all(text.as_bytes())
+			.and_then(|_| self.writer.flush())
+			.map_err(|e| Error::ReportWriteFailed(self.name.clone(), e.to_string()))
+	}
+}
+
+impl FromStr for OutputFormat {
+	type Err = Error;
+
+	fn from_str(format: &str) -> Result<OutputFormat> {
+		match format.trim().to_lowercase().as_str() {
+			"log" => Ok(OutputFormat::Log),
+			"json" => Ok(OutputFormat::Json),
+			"junit" => Ok(OutputFormat::Junit),
+			"tap" => Ok(OutputFormat::Tap),
+			"table" => Ok(OutputFormat::Table),
+			_ => Err(Error::InvalidOutputFormat(format.to_string())),
+		}
+	}
+}
+
+// endregion: methods
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::OutputFormat;
+	use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+	use crate::error::Error;
+	use crate::policy::SuccessPolicy;
+	use serde_json::{json, Value};
+
+	#[test]
+	fn output_format_test() {
+		assert_eq!("json".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+		assert_eq!(" LOG ".parse::<OutputFormat>().unwrap(), OutputFormat::Log);
+		assert!(matches!(
+			"yaml".parse::<OutputFormat>(),
+			Err(Error::InvalidOutputFormat(_))
+		));
+
+		let mut reachable = ProbeResult::new("db:5432", "tcp");
+		reachable.status = ProbeStatus::Success;
+		reachable.success = true;
+		let report = ConnectionReport {
+			results: vec![reachable, ProbeResult::new("cache:6379", "tcp")],
+		};
+		assert_eq!(OutputFormat::Log.render(&report, &SuccessPolicy::All), None);
+
+		let rendered = OutputFormat::Json
+			.render(&report, &SuccessPolicy::Any)
+			.unwrap();
+		let body: Value = serde_json::from_str(&rendered).unwrap();
+		assert_eq!(body["success"], json!(true));
+		assert_eq!(body["outcome"], json!("partial"));
+		assert_eq!(
+			body["connection_report"]["failures"]["failed_hosts_list"],
+			json!(["cache:6379"])
+		);
+		assert_eq!(
+			body["connection_report"]["results"][0]["target"],
+			json!("db:5432")
+		);
+	}
+}
+// endregion: unit tests
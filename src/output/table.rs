@@ -0,0 +1,242 @@
+use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+use crate::policy::{Outcome, SuccessPolicy};
+use ansi_term::{Colour, Style};
+
+// region: constants
+const HEADERS: [&str; 6] = [
+	"TARGET", "PROTOCOL", "ADDRESS", "LATENCY", "STATUS", "ERROR",
+];
+const TARGET: usize = 0;
+const STATUS: usize = 4;
+const ERROR: usize = 5;
+// spaces between columns
+const GAP: usize = 2;
+// however narrow the terminal, targets and errors are not cut shorter than this
+const MIN_TARGET_WIDTH: usize = 20;
+const MIN_ERROR_WIDTH: usize = 12;
+
+// endregion: constants
+
+// region: functions
+// the report as an aligned table, one row per host, ending with a totals footer. Errors and then
+// targets are cut short to fit `width`
+pub fn render(
+	report: &ConnectionReport,
+	success_policy: &SuccessPolicy,
+	color: bool,
+	width: Option<usize>,
+) -> String {
+	let rows = report.results.iter().map(row).collect::<Vec<[String; 6]>>();
+	let mut widths = HEADERS.map(|header| header.len());
+	for row in &rows {
+		for (column, cell) in row.iter().enumerate() {
+			widths[column] = widths[column].max(cell.chars().count());
+		}
+	}
+	if let Some(width) = width {
+		fit(&mut widths, width);
+	}
+
+	let paint = |style: Style, text: String| match color {
+		true => style.paint(text).to_string(),
+		false => text,
+	};
+	let mut table = paint(
+		Style::new().bold(),
+		line(&HEADERS.map(String::from), &widths),
+	);
+	table.push('\n');
+	for (result, row) in report.results.iter().zip(&rows) {
+		let cells = row
+			.iter()
+			.enumerate()
+			.map(|(column, cell)| {
+				let text = cell_text(cell, column, &widths);
+				match column {
+					STATUS => paint(Style::from(status_colour(result.status)), text),
+					_ => text,
+				}
+			})
+			.collect::<Vec<String>>();
+		table.push_str(cells.join(&" ".repeat(GAP)).trim_end());
+		table.push('\n');
+	}
+
+	let table_width = widths.iter().sum::<usize>() + GAP * (widths.len() - 1);
+	table.push_str(&"-".repeat(table_width.min(width.unwrap_or(table_width))));
+	table.push('\n');
+	let outcome = Outcome::from_results(success_policy, &report.results);
+	let outcome_colour = match outcome {
+		Outcome::Success => Colour::Green,
+		Outcome::Partial => Colour::Yellow,
+		Outcome::PolicyFailed => Colour::Red,
+	};
+	table.push_str(&paint(
+		Style::from(outcome_colour).bold(),
+		footer(report, success_policy, outcome),
+	));
+	table.push('\n');
+	table
+}
+
+fn row(result: &ProbeResult) -> [String; 6] {
+	let target = match result.must_fail {
+		true => format!("!{}", result.target),
+		false => result.target.clone(),
+	};
+	let error = match result.status {
+		ProbeStatus::UnexpectedlyReachable => String::from("the host must not be reachable"),
+		_ => result.error.as_deref().unwrap_or("-").replace('\n', " "),
+	};
+	[
+		target,
+		result.protocol.clone(),
+		result
+			.resolved_ip
+			.map(|ip| ip.to_string())
+			.unwrap_or_else(|| String::from("-")),
+		format!("{:.1}ms", result.latency.as_secs_f64() * 1000.0),
+		String::from(status_label(result.status)),
+		error,
+	]
+}
+
+fn line(cells: &[String; 6], widths: &[usize; 6]) -> String {
+	cells
+		.iter()
+		.enumerate()
+		.map(|(column, cell)| cell_text(cell, column, widths))
+		.collect::<Vec<String>>()
+		.join(&" ".repeat(GAP))
+		.trim_end()
+		.to_string()
+}
+
+// a cell cut to its column's width and padded to line up, colours are added after padding
+fn cell_text(cell: &str, column: usize, widths: &[usize; 6]) -> String {
+	let width = widths[column];
+	let text = match cell.chars().count() > width {
+		true => format!(
+			"{}…",
+			cell
+				.chars()
+				.take(width.saturating_sub(1))
+				.collect::<String>()
+		),
+		false => cell.to_string(),
+	};
+	format!("{:<width$}", text, width = width)
+}
+
+// shrink the error column, then the target column, until the table fits
+fn fit(widths: &mut [usize; 6], width: usize) {
+	for (column, min) in [(ERROR, MIN_ERROR_WIDTH), (TARGET, MIN_TARGET_WIDTH)] {
+		let total = widths.iter().sum::<usize>() + GAP * (widths.len() - 1);
+		let over = total.saturating_sub(width);
+		let spare = widths[column].saturating_sub(min);
+		widths[column] -= over.min(spare);
+	}
+}
+
+fn footer(report: &ConnectionReport, success_policy: &SuccessPolicy, outcome: Outcome) -> String {
+	let mut totals = vec![
+		format!("{} successful", report.successful_hosts().len()),
+		format!("{} failed", report.failed_hosts().len()),
+	];
+	for (count, label) in [
+		(report.cancelled_hosts().len(), "cancelled"),
+		(report.blocked_hosts().len(), "correctly blocked"),
+		(
+			report.unexpectedly_reachable_hosts().len(),
+			"unexpectedly reachable",
+		),
+	] {
+		if count > 0 {
+			totals.push(format!("{} {}", count, label));
+		}
+	}
+	let verdict = match outcome {
+		Outcome::Success => "met",
+		Outcome::Partial => "met, but some hosts were unreachable",
+		Outcome::PolicyFailed => "not met",
+	};
+	format!(
+		"{} host(s): {}. Success policy [{}] {}",
+		report.results.len(),
+		totals.join(", "),
+		success_policy,
+		verdict
+	)
+}
+
+fn status_label(status: ProbeStatus) -> &'static str {
+	match status {
+		ProbeStatus::Success => "success",
+		ProbeStatus::Failed => "failed",
+		ProbeStatus::Cancelled => "cancelled",
+		ProbeStatus::Blocked => "blocked",
+		ProbeStatus::UnexpectedlyReachable => "reachable",
+	}
+}
+
+fn status_colour(status: ProbeStatus) -> Colour {
+	match status {
+		ProbeStatus::Success | ProbeStatus::Blocked => Colour::Green,
+		ProbeStatus::Cancelled => Colour::Yellow,
+		ProbeStatus::Failed | ProbeStatus::UnexpectedlyReachable => Colour::Red,
+	}
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::render;
+	use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+	use crate::error::Error;
+	use crate::policy::SuccessPolicy;
+	use std::time::Duration;
+
+	fn report() -> ConnectionReport {
+		let mut reachable = ProbeResult::new("db.internal:5432", "tcp");
+		reachable.status = ProbeStatus::Success;
+		reachable.success = true;
+		reachable.resolved_ip = Some("10.0.0.5".parse().unwrap());
+		reachable.latency = Duration::from_micros(1300);
+		let mut refused = ProbeResult::new("cache.internal:6379", "tcp");
+		refused.set_error(&Error::ConnectionRefused(String::from(
+			"cache.internal:6379",
+		)));
+		ConnectionReport {
+			results: vec![reachable, refused],
+		}
+	}
+
+	#[test]
+	fn table_test() {
+		let table = render(&report(), &SuccessPolicy::Any, false, None);
+		assert_eq!(
+			table,
+			"TARGET               PROTOCOL  ADDRESS   LATENCY  STATUS   ERROR\n\
+			db.internal:5432     tcp       10.0.0.5  1.3ms    success  -\n\
+			cache.internal:6379  tcp       -         0.0ms    failed   connection to cache.internal:6379 was refused\n\
+			--------------------------------------------------------------------------------------------------------\n\
+			2 host(s): 1 successful, 1 failed. Success policy [any] met, but some hosts were unreachable\n"
+		);
+
+		// the error column gives way first on narrow terminals
+		let table = render(&report(), &SuccessPolicy::Any, false, Some(72));
+		let rows = table.lines().collect::<Vec<&str>>();
+		assert_eq!(
+			rows[2],
+			"cache.internal:6379  tcp       -         0.0ms    failed   connection t…"
+		);
+		assert!(rows[..4].iter().all(|row| row.chars().count() <= 72));
+
+		let table = render(&report(), &SuccessPolicy::All, true, None);
+		assert!(table.contains("\u{1b}[31mfailed \u{1b}[0m"));
+		assert!(table.ends_with("Success policy [all] not met\u{1b}[0m\n"));
+	}
+}
+// endregion: unit tests
@@ -0,0 +1,153 @@
+use super::ReportOutput;
+use crate::can_i_connect::{CanIConnect, ConnectionReport, ProbeEvent, ProbeResult, ProbeStatus};
+use crate::error::Result;
+use serde_json::json;
+use std::fmt::Write;
+use tokio::sync::mpsc;
+
+// region: functions
+// the whole report as TAP, hosts in the order they were supplied
+pub fn render(report: &ConnectionReport) -> String {
+	let mut tap = plan(report.results.len());
+	for (index, result) in report.results.iter().enumerate() {
+		tap.push_str(&test_line(index + 1, result));
+	}
+	tap
+}
+
+// check every host, writing the plan up front and a test line as each host finishes. Tests are
+// numbered in the order the hosts finish
+pub async fn stream(
+	can_i_connect: &CanIConnect,
+	output: &mut ReportOutput,
+) -> (ConnectionReport, Result<()>) {
+	let mut written = output.write(&plan(can_i_connect.hosts_total()));
+	let (sender, mut receiver) = mpsc::unbounded_channel();
+	let report = can_i_connect.connection_report_with_events(sender);
+	let lines = async {
+		let mut number = 0;
+		while let Some(event) = receiver.recv().await {
+			if let ProbeEvent::ProbeFinished { result, .. } = event {
+				number += 1;
+				// keep going after a failed write so the report still completes
+				if written.is_ok() {
+					written = output.write(&test_line(number, &result));
+				}
+			}
+		}
+	};
+	let (report, _) = tokio::join!(report, lines);
+	(report, written)
+}
+
+fn plan(hosts_total: usize) -> String {
+	format!("TAP version 13\n1..{}\n", hosts_total)
+}
+
+// `ok`/`not ok` for a host, failures get a YAML diagnostic block
+fn test_line(number: usize, result: &ProbeResult) -> String {
+	// an unescaped `#` would start a directive, e.g. `# SKIP`
+	let mut description = format!("{} [{}]", escape(&result.target), result.protocol);
+	if result.must_fail {
+		description.push_str(" (must fail)");
+	}
+	if result.success {
+		return format!("ok {} - {}\n", number, description);
+	}
+	let mut line = format!("not ok {} - {}\n  ---\n", number, description);
+	let (kind, error) = match result.status {
+		ProbeStatus::UnexpectedlyReachable => (
+			String::from("unexpectedly_reachable"),
+			String::from("connected, but the host must not be reachable"),
+		),
+		_ => (
+			result
+				.error_kind
+				.map(|kind| kind.to_string())
+				.unwrap_or_else(|| String::from("failed")),
+			result
+				.error
+				.clone()
+				.unwrap_or_else(|| String::from("connection failed")),
+		),
+	};
+	// JSON strings are valid YAML scalars, whatever characters the error holds
+	let _ = writeln!(line, "  error_kind: {}", kind);
+	let _ = writeln!(line, "  error: {}", json!(error));
+	let _ = writeln!(
+		line,
+		"  latency_ms: {:.1}",
+		result.latency.as_secs_f64() * 1000.0
+	);
+	let _ = writeln!(line, "  attempts: {}", result.attempts);
+	if let Some(ip) = result.resolved_ip {
+		let _ = writeln!(line, "  resolved_ip: {}", json!(ip));
+	}
+	if let Some(status) = result.http_status {
+		let _ = writeln!(line, "  http_status: {}", status);
+	}
+	if let Some(tag) = &result.tag {
+		let _ = writeln!(line, "  tag: {}", json!(tag));
+	}
+	line.push_str("  ...\n");
+	line
+}
+
+fn escape(text: &str) -> String {
+	text.replace('\\', "\\\\").replace('#', "\\#")
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::render;
+	use crate::can_i_connect::{ConnectionReport, ProbeResult, ProbeStatus};
+	use crate::error::Error;
+	use std::time::Duration;
+
+	#[test]
+	fn tap_test() {
+		let mut reachable = ProbeResult::new("db:5432", "tcp");
+		reachable.status = ProbeStatus::Success;
+		reachable.success = true;
+		let mut refused = ProbeResult::new("cache:6379", "tcp");
+		refused.tag = Some(String::from("cache"));
+		refused.latency = Duration::from_millis(12);
+		refused.attempts = 1;
+		refused.set_error(&Error::ConnectionRefused(String::from("cache:6379")));
+		let report = ConnectionReport {
+			results: vec![reachable, refused],
+		};
+		assert_eq!(
+			render(&report),
+			"TAP version 13\n\
+			1..2\n\
+			ok 1 - db:5432 [tcp]\n\
+			not ok 2 - cache:6379 [tcp]\n\
+			\x20 ---\n\
+			\x20 error_kind: connection_refused\n\
+			\x20 error: \"connection to cache:6379 was refused\"\n\
+			\x20 latency_ms: 12.0\n\
+			\x20 attempts: 1\n\
+			\x20 tag: \"cache\"\n\
+			\x20 ...\n"
+		);
+	}
+
+	#[test]
+	fn tap_escape_test() {
+		let mut reachable = ProbeResult::new("https://example.com/a#todo", "https");
+		reachable.status = ProbeStatus::Success;
+		reachable.success = true;
+		let report = ConnectionReport {
+			results: vec![reachable],
+		};
+		assert_eq!(
+			render(&report),
+			"TAP version 13\n1..1\nok 1 - https://example.com/a\\#todo [https]\n"
+		);
+	}
+}
+// endregion: unit tests
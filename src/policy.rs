@@ -0,0 +1,217 @@
+use crate::can_i_connect::ProbeResult;
+use crate::error::{Error, Result};
+use crate::helpers::was_successful;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// region: constants
+// every host was reachable
+pub const EXIT_SUCCESS: u8 = 0;
+// the success policy was not met
+pub const EXIT_POLICY_FAILED: u8 = 1;
+// invalid arguments / nothing to check
+pub const EXIT_CONFIG_ERROR: u8 = 2;
+// the success policy was met but some hosts were unreachable
+pub const EXIT_PARTIAL_FAILURE: u8 = 3;
+// `wait` could not run the command it was given
+pub const EXIT_COMMAND_FAILED: u8 = 126;
+// `wait` could not find the command it was given
+pub const EXIT_COMMAND_NOT_FOUND: u8 = 127;
+
+// endregion: constants
+
+// region: enums
+// decides whether a connection report as a whole counts as a success
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum SuccessPolicy {
+	// every host must be reachable
+	#[default]
+	All,
+	// at least one host must be reachable
+	Any,
+	// at least N hosts must be reachable
+	AtLeast(usize),
+	// at least N percent of the hosts must be reachable
+	Percent(f64),
+	// at least N hosts of every listed tag group must be reachable
+	PerGroup(Vec<(String, usize)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+	Success,
+	Partial,
+	PolicyFailed,
+}
+
+// endregion: enums
+
+// region: methods
+impl Outcome {
+	pub fn from_results(policy: &SuccessPolicy, results: &[ProbeResult]) -> Outcome {
+		if !was_successful(policy, results) {
+			Outcome::PolicyFailed
+		} else if results.iter().all(|result| result.success) {
+			Outcome::Success
+		} else {
+			Outcome::Partial
+		}
+	}
+	pub fn exit_code(&self) -> u8 {
+		match self {
+			Outcome::Success => EXIT_SUCCESS,
+			Outcome::Partial => EXIT_PARTIAL_FAILURE,
+			Outcome::PolicyFailed => EXIT_POLICY_FAILED,
+		}
+	}
+}
+
+impl FromStr for SuccessPolicy {
+	type Err = Error;
+
+	// accepts `all`, `any`, `<N>`, `<N>%` or `group:<tag>=<N>,<tag>=<N>`
+	fn from_str(policy: &str) -> Result<SuccessPolicy> {
+		let invalid = || Error::InvalidSuccessPolicy(policy.to_string());
+		let trimmed = policy.trim();
+		match trimmed.to_lowercase().as_str() {
+			"all" => return Ok(SuccessPolicy::All),
+			"any" => return Ok(SuccessPolicy::Any),
+			_ => {}
+		}
+		if let Some(groups) = trimmed.strip_prefix("group:") {
+			let groups = groups
+				.split(',')
+				.map(|group| {
+					let (tag, min) = group.split_once('=').ok_or_else(invalid)?;
+					let min = min.trim().parse::<usize>().map_err(|_| invalid())?;
+					match tag.trim() {
+						"" => Err(invalid()),
+						tag => Ok((tag.to_string(), min)),
+					}
+				})
+				.collect::<Result<Vec<(String, usize)>>>()?;
+			return Ok(SuccessPolicy::PerGroup(groups));
+		}
+		if let Some(percent) = trimmed.strip_suffix('%') {
+			return match percent.trim().parse::<f64>() {
+				Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(SuccessPolicy::Percent(percent)),
+				_ => Err(invalid()),
+			};
+		}
+		trimmed
+			.parse::<usize>()
+			.map(SuccessPolicy::AtLeast)
+			.map_err(|_| invalid())
+	}
+}
+
+impl fmt::Display for SuccessPolicy {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SuccessPolicy::All => write!(f, "all"),
+			SuccessPolicy::Any => write!(f, "any"),
+			SuccessPolicy::AtLeast(min) => write!(f, "{}", min),
+			SuccessPolicy::Percent(percent) => write!(f, "{}%", percent),
+			SuccessPolicy::PerGroup(groups) => write!(
+				f,
+				"group:{}",
+				groups
+					.iter()
+					.map(|(tag, min)| format!("{}={}", tag, min))
+					.collect::<Vec<String>>()
+					.join(",")
+			),
+		}
+	}
+}
+
+impl Serialize for SuccessPolicy {
+	fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.collect_str(self)
+	}
+}
+
+impl<'de> Deserialize<'de> for SuccessPolicy {
+	fn deserialize<D>(deserializer: D) -> core::result::Result<SuccessPolicy, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let policy = String::deserialize(deserializer)?;
+		policy.parse().map_err(serde::de::Error::custom)
+	}
+}
+
+// endregion: methods
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::{Outcome, SuccessPolicy};
+	use crate::can_i_connect::ProbeResult;
+
+	fn result(tag: Option<&str>, success: bool) -> ProbeResult {
+		let mut result = ProbeResult::new("example.com:80", "tcp");
+		result.tag = tag.map(String::from);
+		result.success = success;
+		result
+	}
+
+	#[test]
+	fn parse_success_policy_test() {
+		assert_eq!("all".parse::<SuccessPolicy>().unwrap(), SuccessPolicy::All);
+		assert_eq!("ANY".parse::<SuccessPolicy>().unwrap(), SuccessPolicy::Any);
+		assert_eq!(
+			"3".parse::<SuccessPolicy>().unwrap(),
+			SuccessPolicy::AtLeast(3)
+		);
+		assert_eq!(
+			"75%".parse::<SuccessPolicy>().unwrap(),
+			SuccessPolicy::Percent(75.0)
+		);
+		assert_eq!(
+			"group:db=1,cache=2".parse::<SuccessPolicy>().unwrap(),
+			SuccessPolicy::PerGroup(vec![(String::from("db"), 1), (String::from("cache"), 2)])
+		);
+		for invalid in ["most", "150%", "-1", "group:db", "group:=1"] {
+			assert!(
+				invalid.parse::<SuccessPolicy>().is_err(),
+				"expected {} to be rejected",
+				invalid
+			);
+		}
+	}
+
+	#[test]
+	fn outcome_test() {
+		let results = vec![
+			result(Some("db"), true),
+			result(Some("db"), false),
+			result(Some("cache"), false),
+			result(None, true),
+		];
+		let outcome = |policy: &str| Outcome::from_results(&policy.parse().unwrap(), &results);
+		assert_eq!(outcome("all"), Outcome::PolicyFailed);
+		assert_eq!(outcome("any"), Outcome::Partial);
+		assert_eq!(outcome("2"), Outcome::Partial);
+		assert_eq!(outcome("3"), Outcome::PolicyFailed);
+		assert_eq!(outcome("50%"), Outcome::Partial);
+		assert_eq!(outcome("51%"), Outcome::PolicyFailed);
+		assert_eq!(outcome("group:db=1"), Outcome::Partial);
+		assert_eq!(outcome("group:db=1,cache=1"), Outcome::PolicyFailed);
+
+		let all_good = vec![result(None, true)];
+		assert_eq!(
+			Outcome::from_results(&SuccessPolicy::All, &all_good),
+			Outcome::Success
+		);
+		assert_eq!(Outcome::Success.exit_code(), 0);
+		assert_eq!(Outcome::PolicyFailed.exit_code(), 1);
+		assert_eq!(Outcome::Partial.exit_code(), 3);
+	}
+}
+// endregion: unit tests
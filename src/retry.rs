@@ -0,0 +1,93 @@
+use rand::Rng;
+use std::time::Duration;
+
+// region: constants
+pub const DEFAULT_RETRIES: u32 = 0;
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+pub const DEFAULT_RETRY_MAX_DELAY: Duration = Duration::from_secs(10);
+
+// endregion: constants
+
+// region: structs
+// how often, and how patiently, a failed connection attempt is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+	pub retries: u32,
+	pub backoff: Duration,
+	pub max_delay: Duration,
+}
+
+// endregion: structs
+
+// region: methods
+impl Default for RetryPolicy {
+	fn default() -> RetryPolicy {
+		RetryPolicy {
+			retries: DEFAULT_RETRIES,
+			backoff: DEFAULT_RETRY_BACKOFF,
+			max_delay: DEFAULT_RETRY_MAX_DELAY,
+		}
+	}
+}
+
+impl RetryPolicy {
+	// total number of attempts made before giving up on a host
+	pub fn max_attempts(&self) -> u32 {
+		self.retries.saturating_add(1)
+	}
+	// exponential backoff capped at `max_delay`, before jitter is applied
+	pub fn base_delay(&self, attempt: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+		self.backoff.saturating_mul(factor).min(self.max_delay)
+	}
+	// how long to wait after the given (1 based) failed attempt. Uses "equal jitter": half of the
+	// backoff is fixed and the other half is random so retries from many probes don't line up
+	pub fn delay(&self, attempt: u32) -> Duration {
+		let base = self.base_delay(attempt);
+		let half = base / 2;
+		let jitter = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+		half + Duration::from_millis(jitter)
+	}
+}
+
+// endregion: methods
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::RetryPolicy;
+	use std::time::Duration;
+
+	#[test]
+	fn base_delay_test() {
+		let policy = RetryPolicy {
+			retries: 10,
+			backoff: Duration::from_millis(100),
+			max_delay: Duration::from_millis(1000),
+		};
+		assert_eq!(policy.max_attempts(), 11);
+		assert_eq!(policy.base_delay(1), Duration::from_millis(100));
+		assert_eq!(policy.base_delay(2), Duration::from_millis(200));
+		assert_eq!(policy.base_delay(3), Duration::from_millis(400));
+		assert_eq!(policy.base_delay(5), Duration::from_millis(1000));
+		assert_eq!(policy.base_delay(64), Duration::from_millis(1000));
+	}
+
+	#[test]
+	fn delay_jitter_test() {
+		let policy = RetryPolicy {
+			retries: 3,
+			backoff: Duration::from_millis(200),
+			max_delay: Duration::from_secs(10),
+		};
+		for _ in 0..100 {
+			let delay = policy.delay(2);
+			assert!(
+				delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400),
+				"delay out of range: {:?}",
+				delay
+			);
+		}
+	}
+}
+// endregion: unit tests
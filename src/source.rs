@@ -0,0 +1,193 @@
+use crate::dns::AddressFamily;
+use crate::error::{Error, Result};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::{TcpSocket, UdpSocket};
+
+// region: structs
+// where connections are made from. On multi-homed hosts the kernel routing table picks the source
+// address (and so the network path) unless one is set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Source {
+	// local address every connection is made from
+	pub address: Option<IpAddr>,
+	// network interface every socket is bound to (`SO_BINDTODEVICE`, Linux only)
+	pub interface: Option<String>,
+}
+
+// endregion: structs
+
+// region: traits
+// tokio's sockets each have their own `bind_device`
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+trait BindDevice {
+	fn bind_device(&self, interface: Option<&[u8]>) -> io::Result<()>;
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+impl BindDevice for TcpSocket {
+	fn bind_device(&self, interface: Option<&[u8]>) -> io::Result<()> {
+		TcpSocket::bind_device(self, interface)
+	}
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+impl BindDevice for UdpSocket {
+	fn bind_device(&self, interface: Option<&[u8]>) -> io::Result<()> {
+		UdpSocket::bind_device(self, interface)
+	}
+}
+
+// endregion: traits
+
+// region: methods
+impl Source {
+	pub fn is_default(&self) -> bool {
+		self.address.is_none() && self.interface.is_none()
+	}
+	// a source address only reaches hosts of its own family
+	pub fn family(&self, family: AddressFamily) -> AddressFamily {
+		match self.address {
+			Some(IpAddr::V4(_)) => AddressFamily::Ipv4Only,
+			Some(IpAddr::V6(_)) => AddressFamily::Ipv6Only,
+			None => family,
+		}
+	}
+	// a family restricted to the other IP family than the source address can never connect
+	pub fn check_family(&self, family: AddressFamily) -> Result<()> {
+		match (self.address, family) {
+			(Some(ip @ IpAddr::V4(_)), AddressFamily::Ipv6Only)
+			| (Some(ip @ IpAddr::V6(_)), AddressFamily::Ipv4Only) => Err(Error::SourceFamilyConflict(
+				ip.to_string(),
+				family.to_string(),
+			)),
+			_ => Ok(()),
+		}
+	}
+	// hosts without an address of the source address family can't be reached from it
+	pub fn family_error(&self, host: &str, e: Error) -> Error {
+		match (self.address, e) {
+			(Some(ip), Error::NoAddressForFamily(..)) => {
+				Error::SourceFamilyMismatch(ip.to_string(), host.to_string())
+			}
+			(_, e) => e,
+		}
+	}
+	// the local address to bind to when connecting to `remote`, any port will do
+	pub fn local_addr(&self, remote: SocketAddr) -> Result<SocketAddr> {
+		match (self.address, remote) {
+			(Some(ip), remote) if ip.is_ipv4() != remote.is_ipv4() => Err(Error::SourceFamilyMismatch(
+				ip.to_string(),
+				remote.to_string(),
+			)),
+			(Some(ip), _) => Ok(SocketAddr::new(ip, 0)),
+			(None, SocketAddr::V4(_)) => Ok((Ipv4Addr::UNSPECIFIED, 0).into()),
+			(None, SocketAddr::V6(_)) => Ok((Ipv6Addr::UNSPECIFIED, 0).into()),
+		}
+	}
+	// an unconnected TCP socket bound to the source, ready to connect to `remote`
+	pub fn tcp_socket(&self, remote: SocketAddr) -> Result<TcpSocket> {
+		let local = self.local_addr(remote)?;
+		let socket = match remote {
+			SocketAddr::V4(_) => TcpSocket::new_v4(),
+			SocketAddr::V6(_) => TcpSocket::new_v6(),
+		}
+		.map_err(|e| self.bind_error(&e))?;
+		self.bind_device(&socket)?;
+		if self.address.is_some() {
+			socket.bind(local).map_err(|e| self.bind_error(&e))?;
+		}
+		Ok(socket)
+	}
+	// a UDP socket bound to the source, ready to connect to `remote`
+	pub async fn udp_socket(&self, remote: SocketAddr) -> Result<UdpSocket> {
+		let socket = UdpSocket::bind(self.local_addr(remote)?)
+			.await
+			.map_err(|e| self.bind_error(&e))?;
+		self.bind_device(&socket)?;
+		Ok(socket)
+	}
+	#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+	fn bind_device(&self, socket: &impl BindDevice) -> Result<()> {
+		match &self.interface {
+			Some(interface) => socket
+				.bind_device(Some(interface.as_bytes()))
+				.map_err(|e| Error::SourceBindFailed(interface.to_string(), e.to_string())),
+			None => Ok(()),
+		}
+	}
+	#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+	fn bind_device<S>(&self, _socket: &S) -> Result<()> {
+		match &self.interface {
+			Some(interface) => Err(Error::SourceBindFailed(
+				interface.to_string(),
+				String::from("binding to an interface is only supported on Linux"),
+			)),
+			None => Ok(()),
+		}
+	}
+	fn bind_error(&self, e: &io::Error) -> Error {
+		let source = match self.address {
+			Some(ip) => ip.to_string(),
+			None => String::from("the source address"),
+		};
+		Error::SourceBindFailed(source, e.to_string())
+	}
+}
+
+// endregion: methods
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::Source;
+	use crate::dns::AddressFamily;
+	use crate::error::Error;
+	use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+	#[test]
+	fn source_test() {
+		let remote: SocketAddr = "192.0.2.10:443".parse().unwrap();
+		let source = Source::default();
+		assert!(source.is_default());
+		assert_eq!(
+			source.local_addr(remote).unwrap(),
+			"0.0.0.0:0".parse::<SocketAddr>().unwrap()
+		);
+		assert_eq!(
+			source.family(AddressFamily::PreferIpv6),
+			AddressFamily::PreferIpv6
+		);
+
+		let source = Source {
+			address: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5))),
+			interface: None,
+		};
+		assert_eq!(
+			source.local_addr(remote).unwrap(),
+			"10.0.0.5:0".parse::<SocketAddr>().unwrap()
+		);
+		assert_eq!(
+			source.family(AddressFamily::PreferIpv6),
+			AddressFamily::Ipv4Only
+		);
+		assert!(source.check_family(AddressFamily::PreferIpv6).is_ok());
+		assert!(source.check_family(AddressFamily::Ipv4Only).is_ok());
+		assert!(matches!(
+			source.check_family(AddressFamily::Ipv6Only),
+			Err(Error::SourceFamilyConflict(..))
+		));
+		assert!(matches!(
+			source.local_addr("[2001:db8::1]:443".parse().unwrap()),
+			Err(Error::SourceFamilyMismatch(..))
+		));
+		assert!(matches!(
+			source.family_error(
+				"db:5432",
+				Error::NoAddressForFamily(String::from("db:5432"), String::from("ipv4-only"))
+			),
+			Error::SourceFamilyMismatch(..)
+		));
+	}
+}
+// endregion: unit tests
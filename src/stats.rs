@@ -0,0 +1,182 @@
+use crate::helpers::serialize_optional_duration_ms;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+// region: constants
+pub const DEFAULT_SAMPLES: u32 = 1;
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// endregion: constants
+
+// region: structs
+// how many times every host is connected to, and how long to wait between those connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampling {
+	pub samples: u32,
+	pub interval: Duration,
+}
+
+// latency statistics over every sample of a host. The latency fields are empty when no sample
+// connected
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+	pub samples: u32,
+	pub received: u32,
+	pub loss_percent: f64,
+	#[serde(rename = "min_ms", serialize_with = "serialize_optional_duration_ms")]
+	pub min: Option<Duration>,
+	#[serde(rename = "mean_ms", serialize_with = "serialize_optional_duration_ms")]
+	pub mean: Option<Duration>,
+	#[serde(rename = "p50_ms", serialize_with = "serialize_optional_duration_ms")]
+	pub p50: Option<Duration>,
+	#[serde(rename = "p95_ms", serialize_with = "serialize_optional_duration_ms")]
+	pub p95: Option<Duration>,
+	#[serde(rename = "max_ms", serialize_with = "serialize_optional_duration_ms")]
+	pub max: Option<Duration>,
+	// mean difference between the latencies of consecutive successful samples
+	#[serde(
+		rename = "jitter_ms",
+		serialize_with = "serialize_optional_duration_ms"
+	)]
+	pub jitter: Option<Duration>,
+}
+
+// endregion: structs
+
+// region: methods
+impl Default for Sampling {
+	fn default() -> Sampling {
+		Sampling {
+			samples: DEFAULT_SAMPLES,
+			interval: DEFAULT_SAMPLE_INTERVAL,
+		}
+	}
+}
+
+impl LatencyStats {
+	// `latencies` holds one entry per sample in the order they were taken, `None` for a lost sample
+	pub fn from_samples(latencies: &[Option<Duration>]) -> LatencyStats {
+		let received = latencies
+			.iter()
+			.flatten()
+			.copied()
+			.collect::<Vec<Duration>>();
+		let mut sorted = received.clone();
+		sorted.sort();
+		let loss_percent = match latencies.len() {
+			0 => 0.0,
+			samples => (samples - received.len()) as f64 * 100.0 / samples as f64,
+		};
+		let mean = match received.len() {
+			0 => None,
+			n => Some(received.iter().sum::<Duration>() / n as u32),
+		};
+		let jitter = match received.len() {
+			0 => None,
+			1 => Some(Duration::ZERO),
+			n => Some(
+				received
+					.windows(2)
+					.map(|pair| pair[0].abs_diff(pair[1]))
+					.sum::<Duration>()
+					/ (n - 1) as u32,
+			),
+		};
+		LatencyStats {
+			samples: latencies.len() as u32,
+			received: received.len() as u32,
+			loss_percent,
+			min: sorted.first().copied(),
+			mean,
+			p50: percentile(&sorted, 50.0),
+			p95: percentile(&sorted, 95.0),
+			max: sorted.last().copied(),
+			jitter,
+		}
+	}
+}
+
+impl fmt::Display for LatencyStats {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let ms = |duration: Option<Duration>| match duration {
+			Some(duration) => format!("{:.1}", duration.as_secs_f64() * 1000.0),
+			None => String::from("-"),
+		};
+		write!(
+			f,
+			"{}/{} samples, loss {:.1}%, min/mean/p50/p95/max {}/{}/{}/{}/{}ms, jitter {}ms",
+			self.received,
+			self.samples,
+			self.loss_percent,
+			ms(self.min),
+			ms(self.mean),
+			ms(self.p50),
+			ms(self.p95),
+			ms(self.max),
+			ms(self.jitter)
+		)
+	}
+}
+
+// endregion: methods
+
+// region: functions
+// nearest-rank percentile of an already sorted list
+fn percentile(sorted: &[Duration], percent: f64) -> Option<Duration> {
+	if sorted.is_empty() {
+		return None;
+	}
+	let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+	sorted.get(rank.clamp(1, sorted.len()) - 1).copied()
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::LatencyStats;
+	use std::time::Duration;
+
+	fn ms(millis: u64) -> Option<Duration> {
+		Some(Duration::from_millis(millis))
+	}
+
+	#[test]
+	fn latency_stats_test() {
+		let stats = LatencyStats::from_samples(&[ms(10), None, ms(30), ms(20), None]);
+		assert_eq!(stats.samples, 5);
+		assert_eq!(stats.received, 3);
+		assert_eq!(stats.loss_percent, 40.0);
+		assert_eq!(stats.min, ms(10));
+		assert_eq!(stats.mean, ms(20));
+		assert_eq!(stats.p50, ms(20));
+		assert_eq!(stats.p95, ms(30));
+		assert_eq!(stats.max, ms(30));
+		// |10 - 30| and |30 - 20|
+		assert_eq!(stats.jitter, ms(15));
+
+		let many = (1..=100).map(ms).collect::<Vec<Option<Duration>>>();
+		let stats = LatencyStats::from_samples(&many);
+		assert_eq!(stats.p50, ms(50));
+		assert_eq!(stats.p95, ms(95));
+		assert_eq!(stats.jitter, ms(1));
+		assert_eq!(stats.loss_percent, 0.0);
+	}
+
+	#[test]
+	fn latency_stats_all_lost_test() {
+		let stats = LatencyStats::from_samples(&[None, None]);
+		assert_eq!(stats.received, 0);
+		assert_eq!(stats.loss_percent, 100.0);
+		assert_eq!(stats.min, None);
+		assert_eq!(stats.p95, None);
+		assert_eq!(stats.jitter, None);
+		assert_eq!(
+			stats.to_string(),
+			"0/2 samples, loss 100.0%, min/mean/p50/p95/max -/-/-/-/-ms, jitter -ms"
+		);
+	}
+}
+// endregion: unit tests
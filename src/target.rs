@@ -0,0 +1,707 @@
+use crate::can_i_connect::CanIConnect;
+use crate::checker::{target_scheme, CheckerRegistry};
+use crate::dns::AddressFamily;
+use crate::error::{Error, Result};
+use crate::helpers::{parse_duration, split_must_fail, split_tag};
+use ipnet::IpNet;
+use reqwest::Url;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::net::{Ipv6Addr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::time::Duration;
+
+// region: constants
+// how many targets a single CIDR block or port range may expand into
+pub const DEFAULT_MAX_EXPANSION: usize = 256;
+
+// endregion: constants
+
+// region: structs
+// a single host to check, parsed from `[!][<tag>=]<scheme>://<host>[:<port>][/<path>][?<overrides>]`
+// e.g. `!egress=tcp://example.com:22` or `https://example.com/health?expect=200&timeout=2s`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+	// the scheme of the checker that handles the target e.g. `tcp`
+	pub scheme: String,
+	// what the target is reported as
+	pub name: String,
+	// what the checker connects to: the URL of http(s) targets, `<host>:<port>` otherwise
+	pub address: String,
+	pub tag: Option<String>,
+	// the check passes when the target can NOT be connected to
+	pub must_fail: bool,
+	pub overrides: TargetOverrides,
+	// the CIDR block or port range spec this target was expanded from
+	pub expanded_from: Option<String>,
+}
+
+// settings for a single target, taken from the query parameters of its URI. Anything not set falls
+// back to the settings every other target uses
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TargetOverrides {
+	pub timeout: Option<Duration>,
+	pub connect_timeout: Option<Duration>,
+	pub tls_timeout: Option<Duration>,
+	pub retries: Option<u32>,
+	pub samples: Option<u32>,
+	pub sample_interval: Option<Duration>,
+	pub family: Option<AddressFamily>,
+	pub expect: Option<ExpectedStatus>,
+}
+
+// the HTTP status codes a response must have, any status passes when not set
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExpectedStatus {
+	ranges: Vec<(u16, u16)>,
+}
+
+// endregion: structs
+
+// region: methods
+impl Target {
+	// a `--http-hosts` / `--tcp-hosts` style host, `[!][<tag>=]<host>`, handed to the checker as is
+	pub fn new(scheme: &str, spec: &str) -> Target {
+		let (must_fail, host) = split_must_fail(spec);
+		let (tag, host) = split_tag(&host);
+		Target {
+			scheme: scheme.to_lowercase(),
+			name: host.clone(),
+			address: host,
+			tag,
+			must_fail,
+			overrides: TargetOverrides::default(),
+			expanded_from: None,
+		}
+	}
+	// an `--http-hosts` entry, checked by the checker of its URL scheme
+	pub fn http(spec: &str) -> Target {
+		let (_, host) = split_must_fail(spec);
+		let (_, host) = split_tag(&host);
+		Target::new(target_scheme(&host).unwrap_or("http"), spec)
+	}
+	// a `--tcp-hosts` entry: `<host>:<port>`
+	pub fn tcp(spec: &str) -> Target {
+		Target::new("tcp", spec)
+	}
+	fn is_http(&self) -> bool {
+		self.scheme == "http" || self.scheme == "https"
+	}
+	// a tcp target can stand for several: every host address of a CIDR block `10.20.0.0/29:5432`
+	// and/or every port of a range `db.internal:8000-8010`. Fails when that is more than `max` targets
+	pub fn expand(&self, max: usize) -> Result<Vec<Target>> {
+		let invalid = |reason: &str| Error::InvalidTarget(self.name.clone(), reason.to_string());
+		let (host, port) = match self.address.rsplit_once(':') {
+			Some((host, port)) if self.scheme == "tcp" => (host, port),
+			_ => return Ok(vec![self.clone()]),
+		};
+		let is_cidr = host.contains('/');
+		let is_range = port.contains('-');
+		if !is_cidr && !is_range {
+			return Ok(vec![self.clone()]);
+		}
+		let ports: RangeInclusive<u16> = match port.split_once('-') {
+			Some((from, to)) => match (from.parse::<u16>(), to.parse::<u16>()) {
+				(Ok(from), Ok(to)) if from <= to => from..=to,
+				_ => return Err(invalid("port ranges must be <from>-<to> e.g. 8000-8010")),
+			},
+			None => match port.parse::<u16>() {
+				Ok(port) => port..=port,
+				Err(_) => return Err(invalid("the port must be a number")),
+			},
+		};
+		let addresses: Vec<String> = match is_cidr {
+			true => {
+				let net = host
+					.trim_start_matches('[')
+					.trim_end_matches(']')
+					.parse::<IpNet>()
+					.map_err(|_| invalid("expected a CIDR block e.g. 10.20.0.0/29"))?;
+				net
+					.hosts()
+					.flat_map(|ip| {
+						ports
+							.clone()
+							.map(move |port| SocketAddr::new(ip, port).to_string())
+					})
+					.take(max.saturating_add(1))
+					.collect()
+			}
+			false => ports
+				.map(|port| format!("{}:{}", host, port))
+				.take(max.saturating_add(1))
+				.collect(),
+		};
+		if addresses.len() > max {
+			return Err(Error::ExpansionTooLarge(self.name.clone(), max));
+		}
+		let expanded = addresses
+			.into_iter()
+			.map(|address| Target {
+				name: address.clone(),
+				address,
+				expanded_from: Some(self.name.clone()),
+				..self.clone()
+			})
+			.collect();
+		Ok(expanded)
+	}
+}
+
+impl FromStr for Target {
+	type Err = Error;
+
+	fn from_str(spec: &str) -> Result<Target> {
+		let invalid = |reason: String| Error::InvalidTarget(spec.to_string(), reason);
+		let (must_fail, uri) = split_must_fail(spec.trim());
+		let (tag, uri) = split_tag(&uri);
+		let scheme = target_scheme(&uri)
+			.ok_or_else(|| {
+				invalid(String::from(
+					"expected <scheme>://<host> e.g. tcp://example.com:443",
+				))
+			})?
+			.to_lowercase();
+		let mut url = Url::parse(&uri).map_err(|e| invalid(e.to_string()))?;
+		let mut target = Target {
+			scheme,
+			name: uri.clone(),
+			address: String::new(),
+			tag,
+			must_fail,
+			overrides: TargetOverrides::default(),
+			expanded_from: None,
+		};
+		// query parameters that are not overrides belong to the URL of http(s) targets
+		let mut kept = vec![];
+		for (key, value) in url.query_pairs() {
+			if !target.overrides.set(&key, &value).map_err(invalid)? {
+				kept.push((key.into_owned(), value.into_owned()));
+			}
+		}
+		if !target.is_http() {
+			if let Some((key, _)) = kept.first() {
+				return Err(invalid(format!("unknown parameter {}", key)));
+			}
+			if target.overrides.expect.is_some() {
+				return Err(invalid(String::from(
+					"expect only applies to http(s) targets",
+				)));
+			}
+		}
+		match kept.is_empty() {
+			true => url.set_query(None),
+			false => {
+				url.query_pairs_mut().clear().extend_pairs(kept);
+			}
+		}
+		target.address = match target.is_http() {
+			true => url.to_string(),
+			false => match (url.host_str(), url.port_or_known_default()) {
+				(Some(host), Some(port)) if !host.is_empty() => format!("{}:{}", host, port),
+				(None, _) | (Some(""), _) => return Err(invalid(String::from("missing host"))),
+				_ => return Err(invalid(String::from("missing port"))),
+			},
+		};
+		Ok(target)
+	}
+}
+
+impl fmt::Display for Target {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name)
+	}
+}
+
+// targets are supplied as strings in the server mode payload, with their prefixes
+impl Serialize for Target {
+	fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		let must_fail = if self.must_fail { "!" } else { "" };
+		let tag = match &self.tag {
+			Some(tag) => format!("{}=", tag),
+			None => String::new(),
+		};
+		serializer.serialize_str(&format!("{}{}{}", must_fail, tag, self.name))
+	}
+}
+
+impl<'de> Deserialize<'de> for Target {
+	fn deserialize<D>(deserializer: D) -> StdResult<Target, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let spec = String::deserialize(deserializer)?;
+		spec.parse::<Target>().map_err(serde::de::Error::custom)
+	}
+}
+
+impl TargetOverrides {
+	// set the override a query parameter stands for, `false` when it is not an override
+	fn set(&mut self, key: &str, value: &str) -> StdResult<bool, String> {
+		let duration = || {
+			parse_duration(value).ok_or_else(|| {
+				format!(
+					"{} must be a duration e.g. 500ms or 2s but got {}",
+					key, value
+				)
+			})
+		};
+		match key {
+			"timeout" => self.timeout = Some(duration()?),
+			"connect_timeout" => self.connect_timeout = Some(duration()?),
+			"tls_timeout" => self.tls_timeout = Some(duration()?),
+			"retries" => {
+				self.retries = Some(
+					value
+						.parse::<u32>()
+						.map_err(|_| format!("retries must be a number but got {}", value))?,
+				)
+			}
+			"samples" => match value.parse::<u32>() {
+				Ok(samples) if samples > 0 => self.samples = Some(samples),
+				_ => {
+					return Err(format!(
+						"samples must be a number greater than 0 but got {}",
+						value
+					))
+				}
+			},
+			"sample_interval" => self.sample_interval = Some(duration()?),
+			"family" => self.family = Some(value.parse().map_err(|e: Error| e.to_string())?),
+			// repeat the parameter to accept several statuses e.g. `?expect=200&expect=204`
+			"expect" => {
+				let status = value.parse::<ExpectedStatus>()?;
+				self.expect = Some(match self.expect.take() {
+					Some(expect) => expect.or(status),
+					None => status,
+				});
+			}
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
+	pub fn is_empty(&self) -> bool {
+		*self == TargetOverrides::default()
+	}
+	// apply the overrides on top of the settings every other target uses
+	pub fn apply(&self, can_i_connect: &mut CanIConnect) {
+		if let Some(timeout) = self.timeout {
+			can_i_connect.timeouts.total = timeout;
+		}
+		if let Some(timeout) = self.connect_timeout {
+			can_i_connect.timeouts.connect = Some(timeout);
+		}
+		if let Some(timeout) = self.tls_timeout {
+			can_i_connect.timeouts.tls = Some(timeout);
+		}
+		if let Some(retries) = self.retries {
+			can_i_connect.retry.retries = retries;
+		}
+		if let Some(samples) = self.samples {
+			can_i_connect.sampling.samples = samples;
+		}
+		if let Some(interval) = self.sample_interval {
+			can_i_connect.sampling.interval = interval;
+		}
+		if let Some(family) = self.family {
+			can_i_connect.family = family;
+		}
+		if let Some(expect) = &self.expect {
+			can_i_connect.expect_status = Some(expect.clone());
+		}
+	}
+}
+
+impl ExpectedStatus {
+	pub fn matches(&self, status: u16) -> bool {
+		self
+			.ranges
+			.iter()
+			.any(|(from, to)| (*from..=*to).contains(&status))
+	}
+	// accept the statuses of either
+	pub fn or(mut self, other: ExpectedStatus) -> ExpectedStatus {
+		self.ranges.extend(other.ranges);
+		self
+	}
+}
+
+impl FromStr for ExpectedStatus {
+	type Err = String;
+
+	// accepts a status `200`, a class `2xx` or a range `200-299`, lowest status first
+	fn from_str(value: &str) -> StdResult<ExpectedStatus, String> {
+		let invalid = || {
+			format!(
+				"expect must be a status e.g. 200, 2xx or 200-299 but got {}",
+				value
+			)
+		};
+		let status = |s: &str| match s.trim().parse::<u16>() {
+			Ok(status) if (100..=599).contains(&status) => Ok(status),
+			_ => Err(invalid()),
+		};
+		let value = value.trim().to_lowercase();
+		let range = if let Some(class) = value.strip_suffix("xx") {
+			match class.parse::<u16>() {
+				Ok(class) if (1..=5).contains(&class) => (class * 100, class * 100 + 99),
+				_ => return Err(invalid()),
+			}
+		} else if let Some((from, to)) = value.split_once('-') {
+			match (status(from)?, status(to)?) {
+				(from, to) if from > to => {
+					return Err(format!(
+						"expect range must start at the lower status e.g. {}-{} but got {}",
+						to, from, value
+					))
+				}
+				range => range,
+			}
+		} else {
+			let status = status(&value)?;
+			(status, status)
+		};
+		Ok(ExpectedStatus {
+			ranges: vec![range],
+		})
+	}
+}
+
+impl fmt::Display for ExpectedStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let ranges = self
+			.ranges
+			.iter()
+			.map(|(from, to)| match (from, to) {
+				(from, to) if from == to => from.to_string(),
+				(from, to) if from % 100 == 0 && *to == from + 99 => format!("{}xx", from / 100),
+				(from, to) => format!("{}-{}", from, to),
+			})
+			.collect::<Vec<String>>();
+		write!(f, "{}", ranges.join(" or "))
+	}
+}
+
+// endregion: methods
+
+// region: functions
+// split a comma separated list of hosts, ignoring the spaces around every host and empty entries
+// (e.g. after a trailing comma)
+pub fn split_list(list: &str) -> Vec<String> {
+	list
+		.split(',')
+		.map(str::trim)
+		.filter(|host| !host.is_empty())
+		.map(String::from)
+		.collect()
+}
+
+// parse every target, keeping the problem with each one that is invalid
+pub fn parse_targets<S: AsRef<str>>(specs: &[S]) -> (Vec<Target>, Vec<Error>) {
+	let mut targets = vec![];
+	let mut problems = vec![];
+	for spec in specs {
+		match spec.as_ref().parse::<Target>() {
+			Ok(target) => targets.push(target),
+			Err(e) => problems.push(e),
+		}
+	}
+	(targets, problems)
+}
+
+// every problem with the targets that can be found before anything is probed: an unsupported
+// scheme, an invalid URL, a missing or invalid host or port, an IPv6 address without brackets, a
+// CIDR block or port range that is invalid or too large, and duplicates
+pub fn validate_targets(
+	targets: &[Target],
+	checkers: &CheckerRegistry,
+	max_expansion: usize,
+) -> Vec<Error> {
+	let mut problems = vec![];
+	let mut seen = HashSet::new();
+	for target in targets {
+		let invalid = |reason: &str| Error::InvalidTarget(target.name.clone(), reason.to_string());
+		if target.address.chars().any(char::is_whitespace) {
+			problems.push(invalid("contains whitespace"));
+			continue;
+		}
+		let Some(checker) = checkers.get(&target.scheme) else {
+			problems.push(invalid(&format!(
+				"no checker is registered for {}:// targets",
+				target.scheme
+			)));
+			continue;
+		};
+		let expanded = match target.expand(max_expansion) {
+			Ok(expanded) => expanded,
+			Err(e) => {
+				problems.push(e);
+				continue;
+			}
+		};
+		// the targets a CIDR block or port range expands into all look alike
+		if let Some(first) = expanded.first() {
+			let authority = match checker.authority(&first.address) {
+				Ok(authority) => validate_authority(&authority),
+				Err(Error::InvalidUrl(_)) => Err(String::from(
+					"expected a URL e.g. https://example.com/health",
+				)),
+				Err(e) => Err(e.to_string()),
+			};
+			if let Err(reason) = authority {
+				problems.push(invalid(&reason));
+				continue;
+			}
+		}
+		// the same host with different overrides is checked twice on purpose
+		let key = (
+			target.scheme.clone(),
+			target.address.to_lowercase(),
+			target.overrides.clone(),
+		);
+		if !seen.insert(key) {
+			problems.push(invalid("duplicate target"));
+		}
+	}
+	problems
+}
+
+// a `<host>:<port>` with IPv6 addresses in brackets
+fn validate_authority(authority: &str) -> StdResult<(), String> {
+	let (host, port) = match authority.rsplit_once(':') {
+		Some((host, port)) if !port.ends_with(']') => (host, port),
+		_ => return Err(String::from("missing port, expected <host>:<port>")),
+	};
+	match (host.strip_prefix('['), host.strip_suffix(']')) {
+		(Some(_), Some(_)) => {
+			let ip = &host[1..host.len() - 1];
+			if ip.parse::<Ipv6Addr>().is_err() {
+				return Err(format!("{} is not a valid IPv6 address", ip));
+			}
+		}
+		(None, None) if host.is_empty() => return Err(String::from("missing host")),
+		(None, None) if host.contains(':') => {
+			return Err(String::from(
+				"IPv6 addresses must be in brackets e.g. [::1]:443",
+			))
+		}
+		(None, None) => {}
+		_ => return Err(String::from("unbalanced brackets around the IPv6 address")),
+	}
+	match port.parse::<u16>() {
+		Ok(port) if port > 0 => Ok(()),
+		_ => Err(format!("{} is not a valid port", port)),
+	}
+}
+
+// endregion: functions
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::{parse_targets, split_list, validate_targets, ExpectedStatus, Target};
+	use crate::checker::CheckerRegistry;
+	use crate::dns::AddressFamily;
+	use crate::error::Error;
+	use std::time::Duration;
+
+	#[test]
+	fn target_from_str_test() {
+		let target = "!db=tcp://db.internal:5432?timeout=2s&retries=3"
+			.parse::<Target>()
+			.unwrap();
+		assert_eq!(target.scheme, "tcp");
+		assert_eq!(target.address, "db.internal:5432");
+		assert_eq!(target.name, "tcp://db.internal:5432?timeout=2s&retries=3");
+		assert_eq!(target.tag.as_deref(), Some("db"));
+		assert!(target.must_fail);
+		assert_eq!(target.overrides.timeout, Some(Duration::from_secs(2)));
+		assert_eq!(target.overrides.retries, Some(3));
+
+		// unknown parameters stay in the URL of http(s) targets
+		let target = "https://api.internal/health?expect=2xx&expect=304&verbose=1&family=ipv6"
+			.parse::<Target>()
+			.unwrap();
+		assert_eq!(target.address, "https://api.internal/health?verbose=1");
+		assert_eq!(target.overrides.family, Some(AddressFamily::Ipv6Only));
+		let expect = target.overrides.expect.unwrap();
+		assert!(expect.matches(204) && expect.matches(304) && !expect.matches(404));
+		assert_eq!(expect.to_string(), "2xx or 304");
+
+		let target = "tls://[::1]:636".parse::<Target>().unwrap();
+		assert_eq!(target.address, "[::1]:636");
+		assert!(target.overrides.is_empty());
+	}
+
+	#[test]
+	fn invalid_target_test() {
+		for spec in [
+			"db.internal:5432",
+			"tcp://db.internal",
+			"tls://[::1]",
+			"tcp://db.internal:5432?verbose=1",
+			"tcp://db.internal:5432?expect=200",
+			"tcp://db.internal:5432?timeout=soon",
+			"udp://dns:53?samples=0",
+			"https://api/?expect=7xx",
+			"https://api/?expect=300-200",
+		] {
+			assert!(
+				matches!(spec.parse::<Target>(), Err(Error::InvalidTarget(..))),
+				"expected {} to be invalid",
+				spec
+			);
+		}
+	}
+
+	#[test]
+	fn legacy_target_test() {
+		let target = Target::http("!web=HTTPS://example.com/");
+		assert_eq!(target.scheme, "https");
+		assert_eq!(target.address, "HTTPS://example.com/");
+		assert_eq!(target.tag.as_deref(), Some("web"));
+		assert!(target.must_fail);
+		assert_eq!(Target::http("example.com").scheme, "http");
+		assert_eq!(Target::tcp("example.com:22").address, "example.com:22");
+	}
+
+	#[test]
+	fn expand_test() {
+		let addresses = |spec: &str, max: usize| {
+			Target::tcp(spec)
+				.expand(max)
+				.map(|targets| {
+					targets
+						.into_iter()
+						.map(|target| target.address)
+						.collect::<Vec<String>>()
+				})
+				.map_err(|e| e.to_string())
+		};
+		// the network and broadcast addresses are left out
+		assert_eq!(
+			addresses("10.20.0.0/29:5432", 256).unwrap(),
+			vec![
+				"10.20.0.1:5432",
+				"10.20.0.2:5432",
+				"10.20.0.3:5432",
+				"10.20.0.4:5432",
+				"10.20.0.5:5432",
+				"10.20.0.6:5432"
+			]
+		);
+		assert_eq!(
+			addresses("db.internal:8000-8002", 256).unwrap(),
+			vec!["db.internal:8000", "db.internal:8001", "db.internal:8002"]
+		);
+		assert_eq!(
+			addresses("10.0.0.0/31:80-81", 256).unwrap(),
+			vec!["10.0.0.0:80", "10.0.0.0:81", "10.0.0.1:80", "10.0.0.1:81"]
+		);
+		assert_eq!(
+			addresses("[2001:db8::/127]:443", 256).unwrap(),
+			vec!["[2001:db8::]:443", "[2001:db8::1]:443"]
+		);
+		assert_eq!(
+			addresses("db.internal:5432", 1).unwrap(),
+			vec!["db.internal:5432"]
+		);
+
+		let target = Target::tcp("!db=10.20.0.0/30:5432");
+		let expanded = target.expand(256).unwrap();
+		assert_eq!(expanded.len(), 2);
+		assert!(expanded.iter().all(|target| target.must_fail
+			&& target.tag.as_deref() == Some("db")
+			&& target.expanded_from.as_deref() == Some("10.20.0.0/30:5432")));
+
+		assert_eq!(
+			addresses("10.0.0.0/8:22", 256).unwrap_err(),
+			"10.0.0.0/8:22 expands to more than 256 targets, raise --max-expansion to check them all"
+		);
+		assert!(addresses("[2001:db8::/64]:22", 1000).is_err());
+		assert!(addresses("db:8010-8000", 256).is_err());
+		assert!(addresses("10.0.0.0/33:22", 256).is_err());
+	}
+
+	#[test]
+	fn validate_targets_test() {
+		assert_eq!(
+			split_list(" db:5432, cache:6379 ,,"),
+			vec!["db:5432", "cache:6379"]
+		);
+		let (targets, problems) = parse_targets(&["tcp://db:5432", "ldap:389", "tls://ldap:636"]);
+		assert_eq!(targets.len(), 2);
+		assert_eq!(problems.len(), 1);
+
+		let problems = |targets: Vec<Target>| {
+			validate_targets(&targets, &CheckerRegistry::default(), 256)
+				.into_iter()
+				.map(|e| e.to_string())
+				.collect::<Vec<String>>()
+		};
+		let valid = vec![
+			Target::http("https://example.com/health"),
+			Target::http("http://[::1]:8080/"),
+			Target::tcp("db.internal:5432"),
+			Target::tcp("[2001:db8::1]:443"),
+			Target::tcp("10.20.0.0/29:5432"),
+			"udp://dns.internal:53".parse().unwrap(),
+		];
+		assert_eq!(problems(valid), Vec::<String>::new());
+
+		let invalid = vec![
+			Target::http("example.com"),
+			Target::http("ldap://example.com:389"),
+			Target::tcp("db.internal"),
+			Target::tcp("db.internal:http"),
+			Target::tcp(":5432"),
+			Target::tcp("::1:5432"),
+			Target::tcp("[::1:5432"),
+			Target::tcp("[::1]"),
+			Target::tcp("db.internal:5432"),
+			"tcp://db.internal:5432".parse().unwrap(),
+			Target::tcp("10.0.0.0/8:5432"),
+			Target::tcp("db internal:5432"),
+		];
+		assert_eq!(
+			problems(invalid),
+			vec![
+				"example.com is not a valid target: expected a URL e.g. https://example.com/health",
+				"ldap://example.com:389 is not a valid target: no checker is registered for ldap:// targets",
+				"db.internal is not a valid target: missing port, expected <host>:<port>",
+				"db.internal:http is not a valid target: http is not a valid port",
+				":5432 is not a valid target: missing host",
+				"::1:5432 is not a valid target: IPv6 addresses must be in brackets e.g. [::1]:443",
+				"[::1:5432 is not a valid target: unbalanced brackets around the IPv6 address",
+				"[::1] is not a valid target: missing port, expected <host>:<port>",
+				"tcp://db.internal:5432 is not a valid target: duplicate target",
+				"10.0.0.0/8:5432 expands to more than 256 targets, raise --max-expansion to check them all",
+				"db internal:5432 is not a valid target: contains whitespace",
+			]
+		);
+	}
+
+	#[test]
+	fn expected_status_test() {
+		let range = "200-204".parse::<ExpectedStatus>().unwrap();
+		assert!(range.matches(204) && !range.matches(205));
+		assert_eq!(range.to_string(), "200-204");
+		assert_eq!("404".parse::<ExpectedStatus>().unwrap().to_string(), "404");
+		assert!("ok".parse::<ExpectedStatus>().is_err());
+		assert!("99".parse::<ExpectedStatus>().is_err());
+		// a reversed range would match no status at all
+		assert_eq!(
+			"300-200".parse::<ExpectedStatus>(),
+			Err(String::from(
+				"expect range must start at the lower status e.g. 200-300 but got 300-200"
+			))
+		);
+	}
+}
+// endregion: unit tests
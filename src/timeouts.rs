@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+// region: constants
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+// the part of the total timeout a udp check waits for a reply, leaving time to resolve the host
+const UDP_REPLY_SHARE: f64 = 0.9;
+
+// endregion: constants
+
+// region: structs
+// how long a single connection attempt may take. `total` bounds the whole attempt (DNS, connect, TLS
+// handshake and the HTTP response); the connect and TLS phases default to `total` when not set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+	pub total: Duration,
+	// resolving the host and establishing the TCP connection
+	pub connect: Option<Duration>,
+	// the TLS handshake of `tls://` targets. For `https://` targets it extends the connect timeout,
+	// see `http_connect`
+	pub tls: Option<Duration>,
+}
+
+// endregion: structs
+
+// region: methods
+impl Default for Timeouts {
+	fn default() -> Timeouts {
+		Timeouts::new(DEFAULT_TIMEOUT)
+	}
+}
+
+impl Timeouts {
+	pub fn new(total: Duration) -> Timeouts {
+		Timeouts {
+			total,
+			connect: None,
+			tls: None,
+		}
+	}
+	// a phase can never take longer than the whole attempt
+	pub fn connect(&self) -> Duration {
+		self.connect.unwrap_or(self.total).min(self.total)
+	}
+	pub fn tls(&self) -> Duration {
+		self.tls.unwrap_or(self.total).min(self.total)
+	}
+	// how long a udp check waits for a reply. Silence is a result (open|filtered), not a failure, so
+	// it is reported before the total timeout gives up on the attempt
+	pub fn udp_reply(&self) -> Duration {
+		self.connect().min(self.total.mul_f64(UDP_REPLY_SHARE))
+	}
+	// reqwest bounds resolving, connecting and (for `https://`) the TLS handshake with a single
+	// connect timeout, the handshake can't be timed on its own
+	pub fn http_connect(&self, tls: bool) -> Duration {
+		match tls {
+			true => self.connect().saturating_add(self.tls()).min(self.total),
+			false => self.connect(),
+		}
+	}
+}
+
+// endregion: methods
+
+// region: unit tests
+#[cfg(test)]
+pub mod unit_tests {
+	use super::Timeouts;
+	use std::time::Duration;
+
+	#[test]
+	fn timeouts_test() {
+		let timeouts = Timeouts::new(Duration::from_secs(2));
+		assert_eq!(timeouts.connect(), Duration::from_secs(2));
+		assert_eq!(timeouts.tls(), Duration::from_secs(2));
+		assert_eq!(timeouts.http_connect(true), Duration::from_secs(2));
+		assert_eq!(timeouts.udp_reply(), Duration::from_millis(1800));
+
+		let timeouts = Timeouts {
+			total: Duration::from_secs(2),
+			connect: Some(Duration::from_millis(250)),
+			tls: Some(Duration::from_millis(500)),
+		};
+		assert_eq!(timeouts.connect(), Duration::from_millis(250));
+		assert_eq!(timeouts.http_connect(false), Duration::from_millis(250));
+		assert_eq!(timeouts.http_connect(true), Duration::from_millis(750));
+
+		// phases are capped by the total
+		let timeouts = Timeouts {
+			total: Duration::from_millis(100),
+			connect: Some(Duration::from_secs(1)),
+			tls: None,
+		};
+		assert_eq!(timeouts.connect(), Duration::from_millis(100));
+	}
+}
+// endregion: unit tests
@@ -0,0 +1,78 @@
+use crate::can_i_connect::{CanIConnect, ConnectionReport};
+use crate::policy::{Outcome, SuccessPolicy};
+use log::{debug, info};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+// region: constants
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(60);
+
+// endregion: constants
+
+// region: structs
+// the last report of a wait, along with how long it took to get there
+#[derive(Debug, Clone)]
+pub struct WaitReport {
+	pub report: ConnectionReport,
+	pub outcome: Outcome,
+	// how many times the hosts were checked
+	pub rounds: u32,
+	pub waited: Duration,
+}
+
+// endregion: structs
+
+// region: functions
+// check the hosts over and over until the success policy is met or `max_wait` has passed. The wait
+// between rounds backs off according to the retry policy of `can_i_connect` and no round runs past
+// `max_wait`
+pub async fn wait_for(
+	can_i_connect: &CanIConnect,
+	policy: &SuccessPolicy,
+	max_wait: Duration,
+) -> WaitReport {
+	let start = Instant::now();
+	// a max wait too far off to represent is no limit
+	let deadline = start.checked_add(max_wait);
+	let time_left = || match deadline {
+		Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+		None => Duration::MAX,
+	};
+	let mut round_can_i_connect = can_i_connect.clone();
+	let mut rounds = 0;
+	loop {
+		rounds += 1;
+		let remaining = time_left();
+		round_can_i_connect.deadline = Some(match can_i_connect.deadline {
+			Some(deadline) => deadline.min(remaining),
+			None => remaining,
+		});
+		let report = round_can_i_connect.connection_report().await;
+		let outcome = Outcome::from_results(policy, &report.results);
+		let remaining = time_left();
+		if outcome != Outcome::PolicyFailed || remaining.is_zero() {
+			return WaitReport {
+				report,
+				outcome,
+				rounds,
+				waited: start.elapsed(),
+			};
+		}
+		let delay = can_i_connect.retry.delay(rounds).min(remaining);
+		info!(
+			"Waiting for hosts: [{}/{}] reachable after round {}, checking again in {:?}",
+			report.successful_hosts().len(),
+			can_i_connect.hosts_total(),
+			rounds,
+			delay
+		);
+		debug!(
+			"unreachable hosts: {:?}, cancelled hosts: {:?}",
+			report.failed_hosts(),
+			report.cancelled_hosts()
+		);
+		sleep(delay).await;
+	}
+}
+
+// endregion: functions
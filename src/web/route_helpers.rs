@@ -1,8 +1,42 @@
-use super::types::CanIConnectPayload;
-use axum::{extract::Json, http::StatusCode};
+use super::types::{CanIConnectPayload, ServerLimits};
+use crate::can_i_connect::{
+	AddressMode, CanIConnect, ProbeEvent, ProbeResult, DEFAULT_CONCURRENCY,
+};
+use crate::error::Error;
+use crate::helpers::was_successful;
+use crate::policy::{Outcome, SuccessPolicy};
+use crate::retry::RetryPolicy;
+use crate::source::Source;
+use crate::stats::Sampling;
+use crate::target::{parse_targets, Target, TargetOverrides};
+use crate::timeouts::Timeouts;
+use axum::{
+	body::Body,
+	extract::Json,
+	http::{header, HeaderMap, HeaderValue, StatusCode},
+	response::{
+		sse::{Event, KeepAlive, Sse},
+		IntoResponse, Response,
+	},
+};
+use futures::stream::StreamExt;
 use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::fmt::Display;
 use std::result::Result as StdResult;
 
+// region: enums
+// how probe events are streamed back, picked from the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFormat {
+	// one JSON event per line (`application/x-ndjson`)
+	Ndjson,
+	// server-sent events (`text/event-stream`)
+	Sse,
+}
+
+// endregion: enums
+
 // region functions
 pub fn parse_payload(
 	raw_payload: &mut Value,
@@ -15,16 +49,229 @@ pub fn parse_payload(
 	})
 }
 
-pub fn validate_hosts(payload: &CanIConnectPayload) -> StdResult<(), (StatusCode, Json<Value>)> {
-	// Unwrap https_hosts and tcp_hosts, default to empty Vec if None
-	let http_hosts = payload.http_hosts.clone();
-	let tcp_hosts = payload.tcp_hosts.clone();
+// the CanIConnect a request asks for, within the server's limits. Every problem with the hosts is
+// checked up front and reported together
+pub fn build_can_i_connect(
+	payload: &CanIConnectPayload,
+	limits: &ServerLimits,
+) -> StdResult<CanIConnect, (StatusCode, Json<Value>)> {
+	let max_expansion = payload.max_expansion.unwrap_or(limits.max_expansion);
+	if max_expansion > limits.max_expansion {
+		return Err(over_limit(
+			"max_expansion",
+			limits.max_expansion,
+			max_expansion,
+		));
+	}
+	let concurrency = payload
+		.concurrency
+		.unwrap_or(DEFAULT_CONCURRENCY.min(limits.max_concurrency));
+	if concurrency > limits.max_concurrency {
+		return Err(over_limit(
+			"concurrency",
+			limits.max_concurrency,
+			concurrency,
+		));
+	}
 
-	if http_hosts.is_empty() && tcp_hosts.is_empty() {
+	if payload.all_addresses && payload.happy_eyeballs {
 		let error_body = Json(json!({
-			"error": "Both 'http_hosts' and 'tcp_hosts' cannot be empty"
+			"error": "'all_addresses' and 'happy_eyeballs' cannot be used together"
 		}));
 		return Err((StatusCode::BAD_REQUEST, error_body));
 	}
-	Ok(())
+
+	if payload.http_hosts.is_empty()
+		&& payload.tcp_hosts.is_empty()
+		&& payload.must_fail_http_hosts.is_empty()
+		&& payload.must_fail_tcp_hosts.is_empty()
+		&& payload.targets.is_empty()
+	{
+		let error_body = Json(json!({
+			"error": "'http_hosts', 'tcp_hosts' and 'targets' cannot all be empty"
+		}));
+		return Err((StatusCode::BAD_REQUEST, error_body));
+	}
+
+	// must-fail hosts are checked like any other host, marked with the `!` prefix
+	let must_fail = |hosts: &Vec<String>| {
+		hosts
+			.iter()
+			.map(|host| format!("!{}", host))
+			.collect::<Vec<String>>()
+	};
+	let (targets, mut problems) = parse_targets(&payload.targets);
+	// what a target sets for itself e.g. `samples=` counts too
+	for (field, value, limit) in [
+		(
+			"samples",
+			highest(payload.samples, &targets, |overrides| overrides.samples),
+			limits.max_samples,
+		),
+		(
+			"retries",
+			highest(payload.retries, &targets, |overrides| overrides.retries),
+			limits.max_retries,
+		),
+	] {
+		if value > limit {
+			return Err(over_limit(field, limit, value));
+		}
+	}
+	for (field, value, limit) in [
+		(
+			"timeout",
+			highest(payload.timeout, &targets, |overrides| overrides.timeout),
+			limits.max_timeout,
+		),
+		(
+			"retry_max_delay",
+			payload.retry_max_delay,
+			limits.max_retry_max_delay,
+		),
+		(
+			"sample_interval",
+			highest(payload.sample_interval, &targets, |overrides| {
+				overrides.sample_interval
+			}),
+			limits.max_sample_interval,
+		),
+		(
+			"deadline",
+			payload.deadline.unwrap_or_default(),
+			limits.max_deadline,
+		),
+	] {
+		if value > limit {
+			return Err(over_limit(
+				field,
+				humantime::format_duration(limit),
+				humantime::format_duration(value),
+			));
+		}
+	}
+	let mut builder = CanIConnect::builder()
+		.http_hosts(payload.http_hosts.clone())
+		.http_hosts(must_fail(&payload.must_fail_http_hosts))
+		.tcp_hosts(payload.tcp_hosts.clone())
+		.tcp_hosts(must_fail(&payload.must_fail_tcp_hosts))
+		.targets(targets)
+		.max_expansion(max_expansion)
+		.timeouts(Timeouts {
+			total: payload.timeout,
+			connect: payload.connect_timeout,
+			tls: payload.tls_timeout,
+		})
+		.concurrency(concurrency)
+		.retry(RetryPolicy {
+			retries: payload.retries,
+			backoff: payload.retry_backoff,
+			max_delay: payload.retry_max_delay,
+		})
+		.sampling(Sampling {
+			samples: payload.samples,
+			interval: payload.sample_interval,
+		})
+		.address_mode(if payload.all_addresses {
+			AddressMode::All
+		} else if payload.happy_eyeballs {
+			AddressMode::HappyEyeballs
+		} else {
+			AddressMode::First
+		})
+		.family(payload.family)
+		.source(Source {
+			address: payload.source_address,
+			interface: payload.interface.clone(),
+		});
+	if let Some(deadline) = payload.deadline {
+		builder = builder.deadline(deadline);
+	}
+	match builder.build() {
+		Ok(can_i_connect) if problems.is_empty() => return Ok(can_i_connect),
+		Ok(_) => {}
+		Err(Error::InvalidTargets(host_problems)) => problems.extend(host_problems),
+		Err(e) => problems.push(e),
+	}
+	let error_body = Json(json!({
+		"error": Error::InvalidTargets(problems.clone()).to_string(),
+		"problems": problems.iter().map(|e| e.to_string()).collect::<Vec<String>>(),
+	}));
+	Err((StatusCode::BAD_REQUEST, error_body))
+}
+
+// the highest of a request's own value and what its targets set for themselves
+fn highest<T: Ord + Copy>(
+	value: T,
+	targets: &[Target],
+	field: impl Fn(&TargetOverrides) -> Option<T>,
+) -> T {
+	targets
+		.iter()
+		.filter_map(|target| field(&target.overrides))
+		.fold(value, T::max)
+}
+
+// a request asking for more than the server allows is rejected rather than given less
+fn over_limit(field: &str, limit: impl Display, value: impl Display) -> (StatusCode, Json<Value>) {
+	let error_body = Json(json!({
+		"error": format!("'{}' must be at most {} but got {}", field, limit, value)
+	}));
+	(StatusCode::BAD_REQUEST, error_body)
+}
+
+// requests that accept a stream get probe events as they happen instead of one response at the end
+pub fn event_format(headers: &HeaderMap) -> Option<EventFormat> {
+	let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+	if accept.contains("text/event-stream") {
+		Some(EventFormat::Sse)
+	} else if accept.contains("application/x-ndjson") {
+		Some(EventFormat::Ndjson)
+	} else {
+		None
+	}
+}
+
+// stream the events of a connection report. The `report_complete` event also says whether the
+// success policy was met
+pub fn stream_events(
+	can_i_connect: CanIConnect,
+	success_policy: SuccessPolicy,
+	format: EventFormat,
+) -> Response {
+	let mut results: Vec<ProbeResult> = vec![];
+	let events = can_i_connect.event_stream().map(move |event| {
+		let mut value = serde_json::to_value(&event).unwrap_or_default();
+		match event {
+			ProbeEvent::ProbeFinished { result, .. } => results.push(*result),
+			ProbeEvent::ReportComplete { .. } => {
+				value["success"] = json!(was_successful(&success_policy, &results));
+				value["outcome"] = json!(Outcome::from_results(&success_policy, &results));
+				value["success_policy"] = json!(success_policy);
+			}
+			ProbeEvent::ProbeStarted { .. } => {}
+		}
+		value
+	});
+	match format {
+		EventFormat::Ndjson => {
+			let lines = events.map(|value| Ok::<String, Infallible>(format!("{}\n", value)));
+			let mut response = Body::from_stream(lines).into_response();
+			response.headers_mut().insert(
+				header::CONTENT_TYPE,
+				HeaderValue::from_static("application/x-ndjson"),
+			);
+			response
+		}
+		EventFormat::Sse => {
+			let events = events.map(|value| {
+				let name = value["event"].as_str().unwrap_or_default().to_string();
+				Ok::<Event, Infallible>(Event::default().event(name).data(value.to_string()))
+			});
+			// keep-alive comments hold the connection open while a host hangs
+			Sse::new(events)
+				.keep_alive(KeepAlive::default())
+				.into_response()
+		}
+	}
 }
@@ -1,24 +1,30 @@
 use crate::{
-	helpers::{handler_log, was_successful},
-	web::route_helpers::{parse_payload, validate_hosts},
-	CanIConnect,
+	helpers::handler_log,
+	web::route_helpers::{build_can_i_connect, event_format, parse_payload, stream_events},
+	web::types::ServerLimits,
 };
 use axum::{
-	extract::OriginalUri, http::StatusCode, response::IntoResponse, routing::post, Json, Router,
+	extract::{OriginalUri, State},
+	http::{HeaderMap, StatusCode},
+	response::{IntoResponse, Response},
+	routing::post,
+	Json, Router,
 };
 use log::debug;
-use reqwest::Client;
-use serde_json::{json, Value};
-use std::time::Duration;
+use serde_json::Value;
 
-pub fn routes() -> Router {
-	Router::new().route("/can-i-connect", post(can_i_connect_handler))
+pub fn routes(limits: ServerLimits) -> Router {
+	Router::new()
+		.route("/can-i-connect", post(can_i_connect_handler))
+		.with_state(limits)
 }
 
 pub async fn can_i_connect_handler(
+	State(limits): State<ServerLimits>,
 	OriginalUri(original_uri): OriginalUri,
+	headers: HeaderMap,
 	Json(mut raw_payload): Json<Value>,
-) -> impl IntoResponse {
+) -> Result<Response, (StatusCode, Json<Value>)> {
 	let path = original_uri.path();
 	debug!("{}", handler_log(path));
 	debug!("{:?}", raw_payload);
@@ -29,52 +35,20 @@ pub async fn can_i_connect_handler(
 		Err(err) => return Err(err),
 	};
 
-	debug!("timeout: {}", payload.timeout);
+	debug!("timeout: {:?}", payload.timeout);
 	debug!("data: {:?}", payload);
 
-	// Validate hosts
-	validate_hosts(&payload)?;
-
-	// Try to build the HTTP client and handle errors
-	let http_client = match Client::builder()
-		.timeout(Duration::from_secs(payload.timeout as u64))
-		.build()
-	{
-		Ok(client) => Some(client),
-		Err(_) => {
-			let error_body = Json(json!({
-					"error": "Failed to create HTTP client."
-			}));
-			return Err((StatusCode::INTERNAL_SERVER_ERROR, error_body));
-		}
-	};
-
-	// can_i setup
-	let can_i_connect = CanIConnect {
-		http: payload.http_hosts,
-		tcp: payload.tcp_hosts,
-		timeout: payload.timeout,
-		server_mode: false,
-		listen_addr: String::from(""),
-		http_client,
-	};
+	// Validate hosts and set up can_i_connect
+	let can_i_connect = build_can_i_connect(&payload, &limits)?;
 	debug!("{:#?}", can_i_connect);
+	// stream probe events as they happen when the client accepts them
+	if let Some(format) = event_format(&headers) {
+		return Ok(stream_events(can_i_connect, payload.success_policy, format));
+	}
 	// check connectivity and report results
 	let connection_results = can_i_connect.connection_report().await;
 
 	// Create the success body.
-	let resp_payload = Json(json!({
-		"success": was_successful(connection_results.failed_hosts.clone()),
-		"connection_report": {
-			"failures": {
-				"hosts_unreachable": &connection_results.failed_hosts.len(),
-				"failed_hosts_list": &connection_results.failed_hosts,
-			},
-			"successful": {
-				"hosts_reachable": &connection_results.successful_hosts.len(),
-				"successful_hosts_list": &connection_results.successful_hosts,
-			},
-		},
-	}));
-	Ok((StatusCode::OK, resp_payload))
+	let resp_payload = Json(connection_results.to_json(&payload.success_policy));
+	Ok((StatusCode::OK, resp_payload).into_response())
 }
@@ -1,7 +1,28 @@
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use std::net::IpAddr;
+use std::time::Duration;
 
-use crate::options::DEFAULT_TIMEOUT;
+use crate::can_i_connect::DEFAULT_CONCURRENCY;
+use crate::dns::AddressFamily;
+use crate::helpers::parse_duration;
+use crate::policy::SuccessPolicy;
+use crate::retry::{DEFAULT_RETRIES, DEFAULT_RETRY_BACKOFF, DEFAULT_RETRY_MAX_DELAY};
+use crate::stats::{DEFAULT_SAMPLES, DEFAULT_SAMPLE_INTERVAL};
+use crate::target::DEFAULT_MAX_EXPANSION;
+use crate::timeouts::DEFAULT_TIMEOUT;
+
+// region: constants
+// how many times a request may connect to each host
+pub const MAX_SAMPLES: u32 = 100;
+// how long a request may keep the server busy, see `ServerLimits`
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+pub const MAX_RETRIES: u32 = 10;
+pub const MAX_RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+pub const MAX_SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+pub const MAX_DEADLINE: Duration = Duration::from_secs(10 * 60);
+
+// endregion: constants
 
 // region structs
 #[derive(Debug, Deserialize, Serialize)]
@@ -10,45 +31,248 @@ pub struct CanIConnectPayload {
 	pub http_hosts: Vec<String>,
 	#[serde(default = "default_hosts")]
 	pub tcp_hosts: Vec<String>,
+	// hosts that must NOT be reachable
+	#[serde(default = "default_hosts")]
+	pub must_fail_http_hosts: Vec<String>,
+	#[serde(default = "default_hosts")]
+	pub must_fail_tcp_hosts: Vec<String>,
+	// targets of any scheme e.g. `tcp://db:5432?timeout=2s` or `https://api/health?expect=200`,
+	// parsed by `build_can_i_connect` so every invalid one is reported
+	#[serde(default = "default_hosts")]
+	pub targets: Vec<String>,
+	// how many hosts a tcp host with a CIDR block or port range may expand into, at most (and by
+	// default) the server's own `--max-expansion`
+	#[serde(default, deserialize_with = "deserialize_max_expansion")]
+	pub max_expansion: Option<usize>,
+	// total time limit for a connection attempt
 	#[serde(default = "default_timeout", deserialize_with = "deserialize_timeout")]
-	pub timeout: usize,
+	pub timeout: Duration,
+	#[serde(default, deserialize_with = "deserialize_optional_duration")]
+	pub connect_timeout: Option<Duration>,
+	#[serde(default, deserialize_with = "deserialize_optional_duration")]
+	pub tls_timeout: Option<Duration>,
+	// how many hosts to check at the same time, at most the server's own `--concurrency`. By default
+	// `DEFAULT_CONCURRENCY` or the server's `--concurrency` if that is lower
+	#[serde(default, deserialize_with = "deserialize_concurrency")]
+	pub concurrency: Option<usize>,
+	#[serde(default)]
+	pub success_policy: SuccessPolicy,
+	#[serde(default = "default_retries")]
+	pub retries: u32,
+	#[serde(
+		default = "default_retry_backoff",
+		deserialize_with = "deserialize_duration"
+	)]
+	pub retry_backoff: Duration,
+	#[serde(
+		default = "default_retry_max_delay",
+		deserialize_with = "deserialize_duration"
+	)]
+	pub retry_max_delay: Duration,
+	#[serde(default = "default_samples", deserialize_with = "deserialize_samples")]
+	pub samples: u32,
+	#[serde(
+		default = "default_sample_interval",
+		deserialize_with = "deserialize_duration"
+	)]
+	pub sample_interval: Duration,
+	#[serde(default, deserialize_with = "deserialize_optional_duration")]
+	pub deadline: Option<Duration>,
+	#[serde(default)]
+	pub all_addresses: bool,
+	#[serde(default)]
+	pub happy_eyeballs: bool,
+	#[serde(default)]
+	pub family: AddressFamily,
+	// connect from this local address instead of the one the routing table picks
+	#[serde(default)]
+	pub source_address: Option<IpAddr>,
+	// bind every socket to this network interface
+	#[serde(default)]
+	pub interface: Option<String>,
+}
+
+// the most a request may ask of the server, whoever sends it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerLimits {
+	// the server's `--max-expansion`
+	pub max_expansion: usize,
+	// the server's `--concurrency`, how many sockets a request may have open at once
+	pub max_concurrency: usize,
+	// how many samples a request may take of each host, see `MAX_SAMPLES`
+	pub max_samples: u32,
+	// together these bound how long a single request can keep probes running
+	pub max_timeout: Duration,
+	pub max_retries: u32,
+	pub max_retry_max_delay: Duration,
+	pub max_sample_interval: Duration,
+	pub max_deadline: Duration,
+}
+
+impl Default for ServerLimits {
+	fn default() -> ServerLimits {
+		ServerLimits {
+			max_expansion: DEFAULT_MAX_EXPANSION,
+			max_concurrency: DEFAULT_CONCURRENCY,
+			max_samples: MAX_SAMPLES,
+			max_timeout: MAX_TIMEOUT,
+			max_retries: MAX_RETRIES,
+			max_retry_max_delay: MAX_RETRY_MAX_DELAY,
+			max_sample_interval: MAX_SAMPLE_INTERVAL,
+			max_deadline: MAX_DEADLINE,
+		}
+	}
 }
 
-fn default_timeout() -> usize {
+fn default_timeout() -> Duration {
 	DEFAULT_TIMEOUT
 }
 
+fn default_retries() -> u32 {
+	DEFAULT_RETRIES
+}
+
+fn default_retry_backoff() -> Duration {
+	DEFAULT_RETRY_BACKOFF
+}
+
+fn default_retry_max_delay() -> Duration {
+	DEFAULT_RETRY_MAX_DELAY
+}
+
+fn default_samples() -> u32 {
+	DEFAULT_SAMPLES
+}
+
+fn default_sample_interval() -> Duration {
+	DEFAULT_SAMPLE_INTERVAL
+}
+
 fn default_hosts() -> Vec<String> {
 	vec![]
 }
 
-// Custom deserialization function for the timeout field
-fn deserialize_timeout<'de, D>(deserializer: D) -> Result<usize, D::Error>
+// Custom deserialization function for the timeout field, a number of seconds or a duration string
+fn deserialize_timeout<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
 	D: Deserializer<'de>,
 {
-	// Attempt to deserialize the value as a number
 	let value: Value = Value::deserialize(deserializer)?;
 
-	// Check the type of the value and convert accordingly
 	match value {
-		Value::Number(num) => num
-			.as_u64()
-			.map(|n| n as usize)
-			.ok_or_else(|| serde::de::Error::custom("timeout must be a valid number")),
-		Value::String(s) => s
-			.parse::<usize>()
-			.map_err(|_| serde::de::Error::custom("timeout must be a valid number")),
 		Value::Null => Ok(default_timeout()), // Return default if the value is null
+		value => {
+			deserialize_duration(value).map_err(|e| serde::de::Error::custom(format!("timeout: {}", e)))
+		}
+	}
+}
+
+// Custom deserialization function for the concurrency field
+fn deserialize_concurrency<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let value: Value = Value::deserialize(deserializer)?;
+
+	let concurrency = match value {
+		Value::Number(num) => num.as_u64().map(|n| n as usize),
+		Value::String(s) => s.parse::<usize>().ok(),
+		Value::Null => return Ok(None),
+		_ => {
+			return Err(serde::de::Error::custom(
+				"concurrency must be a number or string",
+			))
+		}
+	};
+	match concurrency {
+		Some(n) if n > 0 => Ok(Some(n)),
 		_ => Err(serde::de::Error::custom(
-			"timeout must be a number or string",
+			"concurrency must be a number greater than 0",
 		)),
 	}
 }
 
-#[derive(Debug)]
-pub struct ValidatedHosts {
-	pub http_hosts: Vec<String>,
-	pub tcp_hosts: Vec<String>,
+// Custom deserialization function for the max_expansion field
+fn deserialize_max_expansion<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let value: Value = Value::deserialize(deserializer)?;
+
+	let max_expansion = match value {
+		Value::Number(num) => num.as_u64().map(|n| n as usize),
+		Value::String(s) => s.parse::<usize>().ok(),
+		Value::Null => return Ok(None),
+		_ => {
+			return Err(serde::de::Error::custom(
+				"max_expansion must be a number or string",
+			))
+		}
+	};
+	match max_expansion {
+		Some(n) if n > 0 => Ok(Some(n)),
+		_ => Err(serde::de::Error::custom(
+			"max_expansion must be a number greater than 0",
+		)),
+	}
+}
+
+// Custom deserialization function for the samples field
+fn deserialize_samples<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let value: Value = Value::deserialize(deserializer)?;
+
+	let samples = match value {
+		Value::Number(num) => num.as_u64().and_then(|n| u32::try_from(n).ok()),
+		Value::String(s) => s.parse::<u32>().ok(),
+		Value::Null => return Ok(default_samples()),
+		_ => {
+			return Err(serde::de::Error::custom(
+				"samples must be a number or string",
+			))
+		}
+	};
+	match samples {
+		Some(n) if n > 0 => Ok(n),
+		_ => Err(serde::de::Error::custom(
+			"samples must be a number greater than 0",
+		)),
+	}
+}
+
+// Custom deserialization function for duration fields e.g. `"500ms"`, `"1.5s"` or `2` (seconds)
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	let value: Value = Value::deserialize(deserializer)?;
+
+	let duration = match &value {
+		Value::Number(num) => num
+			.as_f64()
+			.and_then(|n| Duration::try_from_secs_f64(n).ok()),
+		Value::String(s) => parse_duration(s),
+		_ => None,
+	};
+	duration.ok_or_else(|| {
+		serde::de::Error::custom(format!(
+			"expected a duration e.g. \"500ms\", \"1.5s\" or a number of seconds but got {}",
+			value
+		))
+	})
+}
+// Custom deserialization function for optional duration fields, `null` means no limit
+fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	match Option::<Value>::deserialize(deserializer)? {
+		None | Some(Value::Null) => Ok(None),
+		Some(value) => deserialize_duration(value)
+			.map(Some)
+			.map_err(serde::de::Error::custom),
+	}
 }
 // endregion structs